@@ -0,0 +1,387 @@
+// SYNTAX TREE
+// ===========
+// A program as it is written, for tools rather than for running it: every
+// line with its terms, comments and their columns, and the blocks they nest
+// into. Commands map back to their statement through `Program::line`, using
+// the line number `Interpreter::location` reports.
+//
+// Nothing is evaluated, so the tree is built even for programs that do not
+// parse. Lines keep their raw text for the whitespace between pieces.
+
+use crate::parser::{self, CommentKind, Dialect, Lexeme, Token};
+use std::ops::Range;
+
+// Line is 1-based, columns are 1-based and end-exclusive, as in ParseError
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub columns: Range<usize>,
+}
+
+// A whitespace separated term, a bracketed expression being a single one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub text: String,
+    pub span: Span,
+}
+
+// `( inline )` or `; to end of line` comment, delimiters included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+    // Written against the term before it, with no space between
+    pub attached: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Piece {
+    Term(Term),
+    Comment(Comment),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub number: usize,
+    pub source: String,
+    pub dialect: Dialect,
+    // `N10` and `*71` around the line, which the pieces leave out
+    pub line_number: Option<Term>,
+    pub checksum: Option<Term>,
+    pub pieces: Vec<Piece>,
+    pub trailing: Option<Comment>,
+    // An inline comment runs to the end of the line without its `)`
+    pub unterminated: bool,
+}
+impl Line {
+    pub fn parse(number: usize, source: &str, dialect: Dialect) -> Line {
+        let mut line = Line {
+            number,
+            source: source.to_string(),
+            dialect,
+            line_number: None,
+            checksum: None,
+            pieces: Vec::new(),
+            trailing: None,
+            unterminated: false,
+        };
+        let span = |token: &Token, text: &str| Span {
+            line: number,
+            columns: token.column..token.column + text.chars().count(),
+        };
+
+        for lexeme in parser::lex(source) {
+            match lexeme {
+                Lexeme::Term(token) => line.pieces.push(Piece::Term(Term {
+                    text: token.text.to_string(),
+                    span: span(&token, token.text),
+                })),
+                Lexeme::Comment {
+                    token,
+                    kind,
+                    attached,
+                } => {
+                    let text = token.text.trim_end();
+                    let comment = Comment {
+                        text: text.to_string(),
+                        span: span(&token, text),
+                        attached,
+                    };
+
+                    match kind {
+                        CommentKind::ToEnd => line.trailing = Some(comment),
+                        CommentKind::Inline => line.pieces.push(Piece::Comment(comment)),
+                        CommentKind::Unterminated => {
+                            line.pieces.push(Piece::Comment(comment));
+                            line.unterminated = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        line.split_line_number();
+        line
+    }
+
+    // Moves the line number off the first term and the checksum off the last
+    // one, where `parser::line_number_tokens` finds them
+    fn split_line_number(&mut self) {
+        let terms: Vec<Token> = self
+            .terms()
+            .map(|term| Token {
+                text: &term.text,
+                column: term.span.columns.start,
+            })
+            .collect();
+
+        let Some((number, checksum)) = parser::line_number_tokens(&terms) else {
+            return;
+        };
+        let number = number.text.chars().count();
+        let checksum = checksum.map(|checksum| checksum.text.chars().count());
+
+        let index = self.term_index(0).unwrap();
+        let Piece::Term(first) = &self.pieces[index] else {
+            unreachable!()
+        };
+        let (number, rest) = split_term(first, number);
+        self.line_number = Some(number);
+
+        match rest {
+            Some(rest) => self.pieces[index] = Piece::Term(rest),
+            None => {
+                self.pieces.remove(index);
+            }
+        }
+
+        let Some(checksum) = checksum else {
+            return;
+        };
+        let index = self
+            .pieces
+            .iter()
+            .rposition(|piece| matches!(piece, Piece::Term(_)))
+            .unwrap();
+        let Piece::Term(last) = &self.pieces[index] else {
+            unreachable!()
+        };
+        let (rest, checksum) = split_term(last, last.text.chars().count() - checksum);
+        self.checksum = checksum;
+
+        match rest.text.is_empty() {
+            true => {
+                self.pieces.remove(index);
+            }
+            false => self.pieces[index] = Piece::Term(rest),
+        }
+    }
+
+    fn term_index(&self, nth: usize) -> Option<usize> {
+        self.pieces
+            .iter()
+            .enumerate()
+            .filter(|(_, piece)| matches!(piece, Piece::Term(_)))
+            .nth(nth)
+            .map(|(index, _)| index)
+    }
+
+    pub fn terms(&self) -> impl Iterator<Item = &Term> {
+        self.pieces.iter().filter_map(|piece| match piece {
+            Piece::Term(term) => Some(term),
+            Piece::Comment(_) => None,
+        })
+    }
+
+    pub fn comments(&self) -> impl Iterator<Item = &Comment> {
+        self.pieces
+            .iter()
+            .filter_map(|piece| match piece {
+                Piece::Comment(comment) => Some(comment),
+                Piece::Term(_) => None,
+            })
+            .chain(&self.trailing)
+    }
+
+    // First term, the command or keyword of the line
+    pub fn keyword(&self) -> Option<&str> {
+        self.terms().next().map(|term| term.text.as_str())
+    }
+
+    // Term at a 1-based column, or ending right before it
+    pub fn term_at(&self, column: usize) -> Option<&Term> {
+        self.terms()
+            .find(|term| term.span.columns.contains(&column) || term.span.columns.end == column)
+    }
+
+    // Leading whitespace
+    pub fn indent(&self) -> &str {
+        &self.source[..self.source.len() - self.source.trim_start().len()]
+    }
+
+    // Columns of the code and comments, without indentation and trailing
+    // whitespace
+    pub fn span(&self) -> Span {
+        let start = self.indent().chars().count() + 1;
+        let end = self.source.trim_end().chars().count() + 1;
+
+        Span {
+            line: self.number,
+            columns: start..end.max(start),
+        }
+    }
+}
+
+// Splits a term after `count` characters, the rest being None when empty
+fn split_term(term: &Term, count: usize) -> (Term, Option<Term>) {
+    let offset = term
+        .text
+        .char_indices()
+        .nth(count)
+        .map_or(term.text.len(), |(offset, _)| offset);
+    let column = term.span.columns.start + count;
+
+    let head = Term {
+        text: term.text[..offset].to_string(),
+        span: Span {
+            line: term.span.line,
+            columns: term.span.columns.start..column,
+        },
+    };
+    let tail = Term {
+        text: term.text[offset..].to_string(),
+        span: Span {
+            line: term.span.line,
+            columns: column..term.span.columns.end,
+        },
+    };
+
+    (head, Some(tail).filter(|tail| !tail.text.is_empty()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockKind {
+    Sub,
+    Repeat,
+    While,
+    If,
+}
+impl BlockKind {
+    pub fn from_keyword(keyword: &str) -> Option<BlockKind> {
+        match keyword {
+            "SUB" => Some(BlockKind::Sub),
+            "REPEAT" => Some(BlockKind::Repeat),
+            "WHILE" => Some(BlockKind::While),
+            "IF" => Some(BlockKind::If),
+            _ => None,
+        }
+    }
+}
+
+// SUB, REPEAT, WHILE or IF up to its END. `end` is None when the program
+// runs out first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub kind: BlockKind,
+    pub header: Line,
+    pub body: Vec<Node>,
+    pub otherwise: Option<(Line, Vec<Node>)>,
+    pub end: Option<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    // A command, assignment or directive, or a blank or comment-only line
+    Statement(Box<Line>),
+    Block(Box<Block>),
+}
+impl Node {
+    // Lines of the node in source order
+    pub fn lines(&self) -> Vec<&Line> {
+        match self {
+            Node::Statement(line) => vec![line],
+            Node::Block(block) => {
+                let mut lines = vec![&block.header];
+                lines.extend(block.body.iter().flat_map(Node::lines));
+                if let Some((line, body)) = &block.otherwise {
+                    lines.push(line);
+                    lines.extend(body.iter().flat_map(Node::lines));
+                }
+                lines.extend(&block.end);
+                lines
+            }
+        }
+    }
+
+    // From the first line of the node to the end of its last
+    pub fn span(&self) -> Span {
+        let lines = self.lines();
+        let first = lines[0].span();
+        let last = lines[lines.len() - 1].span();
+
+        Span {
+            line: first.line,
+            columns: first.columns.start..last.columns.end,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub nodes: Vec<Node>,
+}
+impl Program {
+    pub fn lines(&self) -> Vec<&Line> {
+        self.nodes.iter().flat_map(Node::lines).collect()
+    }
+
+    // The line with a 1-based number
+    pub fn line(&self, number: usize) -> Option<&Line> {
+        self.lines().into_iter().find(|line| line.number == number)
+    }
+}
+
+// Adds a node to the innermost block still waiting for its END
+fn push(open: &mut [Block], nodes: &mut Vec<Node>, node: Node) {
+    match open.last_mut() {
+        Some(Block {
+            otherwise: Some((_, body)),
+            ..
+        }) => body.push(node),
+        Some(block) => block.body.push(node),
+        None => nodes.push(node),
+    }
+}
+
+// Builds the tree of a program. `%rgcf`/`%gcode` lines switch the dialect as
+// they do for the parser, and G-code sections hold no blocks.
+pub fn parse(source: &str, dialect: Dialect) -> Program {
+    let mut dialect = dialect;
+    let mut nodes: Vec<Node> = Vec::new();
+    let mut open: Vec<Block> = Vec::new();
+
+    for (index, source) in source.lines().enumerate() {
+        if let Some(name) = source.trim().strip_prefix('%') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "rgcf" => dialect = Dialect::Rgcf,
+                "gcode" => dialect = Dialect::GCode,
+                _ => {}
+            }
+        }
+
+        let line = Line::parse(index + 1, source, dialect);
+        let keyword = match dialect {
+            Dialect::Rgcf => line.keyword().unwrap_or(""),
+            Dialect::GCode => "",
+        };
+
+        if let Some(kind) = BlockKind::from_keyword(keyword) {
+            open.push(Block {
+                kind,
+                header: line,
+                body: Vec::new(),
+                otherwise: None,
+                end: None,
+            });
+            continue;
+        }
+
+        match (keyword, open.last_mut()) {
+            ("ELSE", Some(block)) if block.kind == BlockKind::If && block.otherwise.is_none() => {
+                block.otherwise = Some((line, Vec::new()));
+            }
+            ("END", Some(block)) => {
+                block.end = Some(line);
+                let block = open.pop().unwrap();
+                push(&mut open, &mut nodes, Node::Block(Box::new(block)));
+            }
+            _ => push(&mut open, &mut nodes, Node::Statement(Box::new(line))),
+        }
+    }
+
+    while let Some(block) = open.pop() {
+        push(&mut open, &mut nodes, Node::Block(Box::new(block)));
+    }
+
+    Program { nodes }
+}
@@ -0,0 +1,453 @@
+// COMPILED PROGRAMS (.rgcb)
+// =========================
+// A program run once and stored as the commands it produced, so it can be
+// checked on one machine and streamed from another without parsing again.
+//
+// Header, little-endian, 52 bytes:
+//
+// magic      4   "RGCB"
+// version    2   VERSION
+// reserved   2   0
+// profile    8   profile_hash of the robot profile and arm it was checked
+//                against
+// count      4   number of commands
+// mac       32   HMAC-SHA256 of the header before it and of the body
+//
+// The body holds each command as a one byte opcode followed by its values as
+// f32, a fixed number per opcode. The MAC is keyed with a secret shared by the
+// machine compiling programs and the one running them, so a file changed by
+// anyone without the key is rejected. Decoding still checks every value
+// against the profile and the arm's reach, in case a file was signed with a
+// leaked key or by an older build.
+
+use crate::command::{Axis, Command};
+use crate::error::{Origin, ParseError, ParseReport};
+use crate::interpreter::{Interpreter, Lines};
+use crate::kinematics::{Arm, Reach, ReachError};
+use crate::lint::{Diagnostic, Limits, LintKind, Linter, RobotProfile};
+use std::fmt;
+
+pub const EXTENSION: &str = "rgcb";
+pub const MAGIC: &[u8; 4] = b"RGCB";
+pub const VERSION: u16 = 2;
+pub const HEADER_SIZE: usize = 52;
+pub const MAC_SIZE: usize = 32;
+
+// Opcodes, MN having one per axis
+const NO: u8 = 0x00;
+const HM: u8 = 0x01;
+const TG: u8 = 0x02;
+const CL: u8 = 0x03;
+const DW: u8 = 0x04;
+const RH: u8 = 0x05;
+const RS: u8 = 0x06;
+const FS: u8 = 0x07;
+const FR: u8 = 0x08;
+const AC: u8 = 0x09;
+const WI: u8 = 0x0A;
+const TP: u8 = 0x0B;
+const MN_X: u8 = 0x10;
+const MN_Y: u8 = 0x11;
+const MN_Z: u8 = 0x12;
+const MN_A: u8 = 0x13;
+const MN_B: u8 = 0x14;
+const MN_C: u8 = 0x15;
+
+// Number of f32 values following an opcode
+fn payload_size(opcode: u8) -> Option<usize> {
+    match opcode {
+        NO | RH | RS | FS | WI => Some(0),
+        DW | FR | AC | MN_X | MN_Y | MN_Z | MN_A | MN_C => Some(1),
+        MN_B => Some(2),
+        HM | TG => Some(3),
+        CL => Some(5),
+        TP => Some(6),
+        _ => None,
+    }
+}
+
+fn opcode(command: &Command) -> (u8, Vec<f32>) {
+    match *command {
+        Command::NO => (NO, vec![]),
+        Command::HM(x, y, z) => (HM, vec![x, y, z]),
+        Command::TG(x, y, z) => (TG, vec![x, y, z]),
+        Command::TP(x, y, z, roll, pitch, yaw) => (TP, vec![x, y, z, roll, pitch, yaw]),
+        Command::CL(open, force, speed, accel, hold) => (CL, vec![open, force, speed, accel, hold]),
+        Command::DW(ms) => (DW, vec![ms]),
+        Command::MN(Axis::X(angle)) => (MN_X, vec![angle]),
+        Command::MN(Axis::Y(angle)) => (MN_Y, vec![angle]),
+        Command::MN(Axis::Z(angle)) => (MN_Z, vec![angle]),
+        Command::MN(Axis::A(angle)) => (MN_A, vec![angle]),
+        Command::MN(Axis::B(pitch, roll)) => (MN_B, vec![pitch, roll]),
+        Command::MN(Axis::C(angle)) => (MN_C, vec![angle]),
+        Command::RH => (RH, vec![]),
+        Command::RS => (RS, vec![]),
+        Command::FS => (FS, vec![]),
+        Command::FR(feed) => (FR, vec![feed]),
+        Command::AC(accel) => (AC, vec![accel]),
+        Command::WI => (WI, vec![]),
+    }
+}
+
+// Command of an opcode known to `payload_size`, with its values
+fn command(opcode: u8, values: &[f32]) -> Command {
+    match opcode {
+        HM => Command::HM(values[0], values[1], values[2]),
+        TG => Command::TG(values[0], values[1], values[2]),
+        TP => Command::TP(
+            values[0], values[1], values[2], values[3], values[4], values[5],
+        ),
+        CL => Command::CL(values[0], values[1], values[2], values[3], values[4]),
+        DW => Command::DW(values[0]),
+        MN_X => Command::MN(Axis::X(values[0])),
+        MN_Y => Command::MN(Axis::Y(values[0])),
+        MN_Z => Command::MN(Axis::Z(values[0])),
+        MN_A => Command::MN(Axis::A(values[0])),
+        MN_B => Command::MN(Axis::B(values[0], values[1])),
+        MN_C => Command::MN(Axis::C(values[0])),
+        RH => Command::RH,
+        RS => Command::RS,
+        FS => Command::FS,
+        FR => Command::FR(values[0]),
+        AC => Command::AC(values[0]),
+        WI => Command::WI,
+        _ => Command::NO,
+    }
+}
+
+// HMAC-SHA256 of the header up to the MAC and of the body
+fn mac(bytes: &[u8], key: &[u8]) -> [u8; MAC_SIZE] {
+    let mut mac = hmac_sha256::HMAC::new(key);
+    mac.update(&bytes[..HEADER_SIZE - MAC_SIZE]);
+    mac.update(&bytes[HEADER_SIZE..]);
+    mac.finalize()
+}
+
+// FNV-1a of every limit of a profile and of the arm's geometry, so a program
+// checked against one arm is not run on another
+pub fn profile_hash(profile: &RobotProfile, arm: &Arm) -> u64 {
+    let limits: [Limits; 11] = [
+        profile.x,
+        profile.y,
+        profile.z,
+        profile.a,
+        profile.pitch,
+        profile.roll,
+        profile.claw_open,
+        profile.claw_force,
+        profile.claw_speed,
+        profile.claw_accel,
+        profile.claw_hold,
+    ];
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+
+    let geometry = arm
+        .links
+        .iter()
+        .flat_map(|link| [link.a, link.alpha, link.d, link.offset]);
+
+    for value in limits
+        .iter()
+        .flat_map(|limits| [limits.min, limits.max])
+        .chain(geometry)
+    {
+        for byte in value.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01B3);
+        }
+    }
+
+    hash
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    ProfileMismatch,
+    BadSignature,
+    Truncated,
+    UnknownOpcode { opcode: u8, offset: usize },
+    NotFinite { offset: usize },
+    TrailingBytes,
+    Limits(Vec<Diagnostic>),
+    Unreachable(Vec<ReachError>),
+}
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::BadMagic => write!(f, "not a compiled program"),
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "format version {} is not supported", version)
+            }
+            DecodeError::ProfileMismatch => {
+                write!(f, "program was compiled for a different robot profile")
+            }
+            DecodeError::BadSignature => write!(
+                f,
+                "signature does not match, the file was changed or signed with another key"
+            ),
+            DecodeError::Truncated => write!(f, "file ends in the middle of a command"),
+            DecodeError::UnknownOpcode { opcode, offset } => {
+                write!(f, "unknown opcode 0x{:02X} at byte {}", opcode, offset)
+            }
+            DecodeError::NotFinite { offset } => {
+                write!(f, "value at byte {} is not a finite number", offset)
+            }
+            DecodeError::TrailingBytes => write!(f, "bytes left over after the last command"),
+            DecodeError::Limits(diagnostics) => write_limits(f, diagnostics),
+            DecodeError::Unreachable(errors) => write_unreachable(f, errors),
+        }
+    }
+}
+impl std::error::Error for DecodeError {}
+
+pub fn encode(commands: &[Command], profile: &RobotProfile, arm: &Arm, key: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + commands.len() * 5);
+
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+    bytes.extend_from_slice(&profile_hash(profile, arm).to_le_bytes());
+    bytes.extend_from_slice(&(commands.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&[0; MAC_SIZE]);
+
+    for command in commands {
+        let (opcode, values) = opcode(command);
+        bytes.push(opcode);
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    let mac = mac(&bytes, key);
+    bytes[HEADER_SIZE - MAC_SIZE..HEADER_SIZE].copy_from_slice(&mac);
+
+    bytes
+}
+
+// Commands of a compiled program, once its signature, profile and values have
+// been checked. Limit and reach errors are numbered by command, matching the
+// lines of `decompile`.
+pub fn decode(
+    bytes: &[u8],
+    profile: &RobotProfile,
+    arm: &Arm,
+    key: &[u8],
+) -> Result<Vec<Command>, DecodeError> {
+    if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+        return Err(DecodeError::BadMagic);
+    }
+
+    let u16_at = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+    let u32_at = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+    let version = u16_at(4);
+    if version != VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+
+    let expected: [u8; MAC_SIZE] = bytes[HEADER_SIZE - MAC_SIZE..HEADER_SIZE]
+        .try_into()
+        .unwrap();
+    if !hmac_sha256::HMAC::verify(
+        [&bytes[..HEADER_SIZE - MAC_SIZE], &bytes[HEADER_SIZE..]].concat(),
+        key,
+        &expected,
+    ) {
+        return Err(DecodeError::BadSignature);
+    }
+
+    if bytes[8..16] != profile_hash(profile, arm).to_le_bytes() {
+        return Err(DecodeError::ProfileMismatch);
+    }
+
+    let count = u32_at(16) as usize;
+    let mut commands = Vec::with_capacity(count.min(bytes.len()));
+    let mut offset = HEADER_SIZE;
+
+    for _ in 0..count {
+        let opcode = *bytes.get(offset).ok_or(DecodeError::Truncated)?;
+        let size = payload_size(opcode).ok_or(DecodeError::UnknownOpcode { opcode, offset })?;
+        let payload = bytes
+            .get(offset + 1..offset + 1 + size * 4)
+            .ok_or(DecodeError::Truncated)?;
+
+        let values: Vec<f32> = payload
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+
+        if let Some(index) = values.iter().position(|value| !value.is_finite()) {
+            return Err(DecodeError::NotFinite {
+                offset: offset + 1 + index * 4,
+            });
+        }
+
+        commands.push(command(opcode, &values));
+        offset += 1 + size * 4;
+    }
+
+    if offset != bytes.len() {
+        return Err(DecodeError::TrailingBytes);
+    }
+
+    let mut checks = Checks::new(profile, arm);
+    let origin = Origin::default();
+    for (index, command) in commands.iter().enumerate() {
+        checks.command(index + 1, &origin, *command);
+    }
+
+    match checks.finish() {
+        (limits, _) if !limits.is_empty() => Err(DecodeError::Limits(limits)),
+        (_, unreachable) if !unreachable.is_empty() => Err(DecodeError::Unreachable(unreachable)),
+        _ => Ok(commands),
+    }
+}
+
+// Joint and claw limits and the reach of each command, checked when a program
+// is compiled and again when it is decoded
+struct Checks<'a> {
+    linter: Linter<'a>,
+    reach: Reach<'a>,
+    unreachable: Vec<ReachError>,
+}
+impl<'a> Checks<'a> {
+    fn new(profile: &'a RobotProfile, arm: &'a Arm) -> Checks<'a> {
+        Checks {
+            linter: Linter::new(profile),
+            reach: Reach::new(arm, profile),
+            unreachable: Vec::new(),
+        }
+    }
+
+    fn command(&mut self, line: usize, origin: &Origin, command: Command) {
+        self.linter.command(line, origin, &command);
+
+        if let Err(reason) = self.reach.command(&command) {
+            self.unreachable.push(ReachError {
+                line,
+                origin: origin.clone(),
+                command,
+                reason,
+            });
+        }
+    }
+
+    // Style warnings are left to `lint`, only limits reject a program
+    fn finish(self) -> (Vec<Diagnostic>, Vec<ReachError>) {
+        let limits = self
+            .linter
+            .finish()
+            .into_iter()
+            .filter(|diagnostic| {
+                matches!(
+                    diagnostic.kind,
+                    LintKind::JointLimit(..) | LintKind::ClawOutOfRange(..)
+                )
+            })
+            .collect();
+
+        (limits, self.unreachable)
+    }
+}
+
+fn write_limits(f: &mut fmt::Formatter, diagnostics: &[Diagnostic]) -> fmt::Result {
+    writeln!(
+        f,
+        "{} command(s) outside the robot profile:",
+        diagnostics.len()
+    )?;
+
+    for diagnostic in diagnostics {
+        writeln!(f, "  {}", diagnostic)?;
+    }
+
+    Ok(())
+}
+
+fn write_unreachable(f: &mut fmt::Formatter, errors: &[ReachError]) -> fmt::Result {
+    writeln!(f, "{} target(s) the arm cannot reach:", errors.len())?;
+
+    for error in errors {
+        writeln!(f, "  {}", error)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    Parse(ParseReport),
+    Unbounded(usize),
+    Limits(Vec<Diagnostic>),
+    Unreachable(Vec<ReachError>),
+}
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::Parse(report) => write!(f, "{}", report),
+            CompileError::Unbounded(limit) => write!(
+                f,
+                "program keeps running after {} command(s) and cannot be compiled",
+                limit
+            ),
+            CompileError::Limits(diagnostics) => write_limits(f, diagnostics),
+            CompileError::Unreachable(errors) => write_unreachable(f, errors),
+        }
+    }
+}
+impl std::error::Error for CompileError {}
+
+// Runs a program to its end and compiles the commands it produced, signed with
+// `key`. A program producing more than `limit` commands cannot be compiled,
+// and neither can one that breaks a joint or claw limit or has a target the
+// arm cannot reach.
+pub fn compile<I: Lines>(
+    mut program: Interpreter<I>,
+    profile: &RobotProfile,
+    arm: &Arm,
+    key: &[u8],
+    limit: usize,
+) -> Result<Vec<u8>, CompileError> {
+    let mut commands = Vec::new();
+    let mut errors: Vec<ParseError> = Vec::new();
+    let mut checks = Checks::new(profile, arm);
+
+    while let Some(result) = program.next() {
+        match result {
+            Ok(_) if commands.len() == limit => return Err(CompileError::Unbounded(limit)),
+            Ok(command) => {
+                let (line, origin) = program.location().unwrap();
+                checks.command(line, origin, command);
+                commands.push(command);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(CompileError::Parse(ParseReport { errors }));
+    }
+
+    match checks.finish() {
+        (limits, _) if !limits.is_empty() => Err(CompileError::Limits(limits)),
+        (_, unreachable) if !unreachable.is_empty() => Err(CompileError::Unreachable(unreachable)),
+        _ => Ok(encode(&commands, profile, arm, key)),
+    }
+}
+
+// Canonical .rgcf text of a compiled program, one command per line
+pub fn decompile(
+    bytes: &[u8],
+    profile: &RobotProfile,
+    arm: &Arm,
+    key: &[u8],
+) -> Result<String, DecodeError> {
+    let commands = decode(bytes, profile, arm, key)?;
+
+    Ok(commands
+        .iter()
+        .map(|command| format!("{}\n", command))
+        .collect())
+}
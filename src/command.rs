@@ -0,0 +1,95 @@
+// No Op
+// Homing
+// Target
+// Target Pose
+// Claw
+// Dwell
+// Manual
+// Return Home
+// Reset
+// Force Stop
+// Feed Rate
+// Acceleration
+// Wait Until Idle
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    X(f32),
+    Y(f32),
+    Z(f32),
+    A(f32),
+    B(f32, f32),
+    C(f32),
+}
+impl fmt::Display for Axis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Axis::X(value) => write!(f, "X {}", value),
+            Axis::Y(value) => write!(f, "Y {}", value),
+            Axis::Z(value) => write!(f, "Z {}", value),
+            Axis::A(value) => write!(f, "A {}", value),
+            Axis::B(pitch, roll) => write!(f, "B {} {}", pitch, roll),
+            Axis::C(value) => write!(f, "C {}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    NO,
+    HM(f32, f32, f32),
+    TG(f32, f32, f32),
+    TP(f32, f32, f32, f32, f32, f32),
+    CL(f32, f32, f32, f32, f32),
+    DW(f32),
+    MN(Axis),
+    RH,
+    RS,
+    FS,
+    FR(f32),
+    AC(f32),
+    WI,
+}
+// Canonical .rgcf text, which parses back into the same command. Numbers are
+// printed in their shortest form that reads back as the same f32.
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::NO => write!(f, "NO"),
+            Command::HM(x, y, z) => write!(f, "HM {} {} {}", x, y, z),
+            Command::TG(x, y, z) => write!(f, "TG {} {} {}", x, y, z),
+            Command::TP(x, y, z, roll, pitch, yaw) => {
+                write!(f, "TP {} {} {} {} {} {}", x, y, z, roll, pitch, yaw)
+            }
+            Command::CL(open, force, speed, accel, hold) => {
+                write!(f, "CL {} {} {} {} {}", open, force, speed, accel, hold)
+            }
+            Command::DW(milliseconds) => write!(f, "DW {}", milliseconds),
+            Command::MN(axis) => write!(f, "MN {}", axis),
+            Command::RH => write!(f, "RH"),
+            Command::RS => write!(f, "RS"),
+            Command::FS => write!(f, "FS"),
+            Command::FR(feed) => write!(f, "FR {}", feed),
+            Command::AC(accel) => write!(f, "AC {}", accel),
+            Command::WI => write!(f, "WI"),
+        }
+    }
+}
+
+// CL OPEN FORCE SPEED ACCEL HOLD
+// Jaw opening (mm), grip force, jaw speed, jaw acceleration and the time (ms)
+// to hold before the next command. Values used when a caller leaves them out.
+pub const CLAW_OPEN: f32 = 30.0;
+pub const CLAW_FORCE: f32 = 2.0;
+pub const CLAW_SPEED: f32 = 10.0;
+pub const CLAW_ACCEL: f32 = 5.0;
+pub const CLAW_HOLD: f32 = 0.0;
+
+// TP X Y Z ROLL PITCH YAW
+// Tool position (mm) and orientation (degrees, see `kinematics::Pose`). Left
+// out, the orientation is the tool pointing straight down, jaws across x.
+pub const TOOL_ROLL: f32 = 180.0;
+pub const TOOL_PITCH: f32 = 0.0;
+pub const TOOL_YAW: f32 = 0.0;
@@ -0,0 +1,236 @@
+use crate::parser::Token;
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    UnknownMnemonic,
+    WrongArity { expected: usize, found: usize },
+    BadNumber,
+    UnknownAxis,
+    UnterminatedComment,
+    BadChecksum(u8),
+    LineNumberOrder(u64),
+    UnknownDialect,
+    UnknownUnit,
+    UnsupportedCode,
+    UnknownPosition,
+    UnknownParameter,
+    DuplicateParameter,
+    MissingParameter(String),
+    NotPositive,
+    Negative,
+    PositionalAfterNamed,
+    BadExpression(String),
+    UndefinedVariable,
+    UndefinedPose,
+    UnknownFunction,
+    UndefinedSubroutine,
+    DuplicateDefinition,
+    RecursionLimit,
+    UnterminatedBlock,
+    UnexpectedEnd,
+    NestedDefinition,
+    RunawayLoop,
+    IncludeFailed(String),
+    ReadFailed(String),
+    IncludeCycle,
+    IncludeInBlock,
+}
+
+// An INCLUDE line that pulled a file into the program
+#[derive(Debug, Clone, PartialEq)]
+pub struct Include {
+    pub file: Option<PathBuf>,
+    pub line: usize,
+}
+
+// File a line was read from, if any, and the INCLUDE lines that led to it,
+// outermost first
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Origin {
+    pub file: Option<PathBuf>,
+    pub included_from: Vec<Include>,
+}
+
+// Line is 1-based, columns are 1-based and end-exclusive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub columns: Range<usize>,
+    pub token: String,
+    pub kind: ParseErrorKind,
+    pub origin: Box<Origin>,
+}
+impl ParseError {
+    pub fn new(line: usize, token: &Token, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            line,
+            columns: token.columns(),
+            token: token.text.to_string(),
+            kind,
+            origin: Box::default(),
+        }
+    }
+
+    pub fn message(&self) -> Message<'_> {
+        Message(self)
+    }
+
+    // Places an error found on a line of `origin`, unless it already has one
+    pub fn at(mut self, origin: &Origin) -> ParseError {
+        if *self.origin == Origin::default() {
+            *self.origin = origin.clone();
+        }
+        self
+    }
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.origin.file {
+            write!(f, "{} ", file.display())?;
+        }
+
+        write!(
+            f,
+            "line {}, col {}-{}: ",
+            self.line, self.columns.start, self.columns.end
+        )?;
+
+        write!(f, "{}", self.message())?;
+
+        for include in self.origin.included_from.iter().rev() {
+            match &include.file {
+                Some(file) => write!(
+                    f,
+                    "\n    included from {} line {}",
+                    file.display(),
+                    include.line
+                )?,
+                None => write!(f, "\n    included from line {}", include.line)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+impl std::error::Error for ParseError {}
+
+// What went wrong, without the location, e.g. for editors that place it
+// themselves
+pub struct Message<'a>(&'a ParseError);
+impl fmt::Display for Message<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0.kind {
+            ParseErrorKind::UnknownMnemonic => write!(f, "unknown command '{}'", self.0.token),
+            ParseErrorKind::WrongArity { expected, found } => write!(
+                f,
+                "'{}' expects {} argument(s), found {}",
+                self.0.token, expected, found
+            ),
+            ParseErrorKind::BadNumber => write!(f, "'{}' is not a valid number", self.0.token),
+            ParseErrorKind::UnknownAxis => write!(f, "unknown axis '{}'", self.0.token),
+            ParseErrorKind::UnknownDialect => write!(f, "unknown dialect '{}'", self.0.token),
+            ParseErrorKind::UnknownUnit => write!(
+                f,
+                "unknown unit '{}', expected MM, IN, DEG, RAD or STEP",
+                self.0.token
+            ),
+            ParseErrorKind::UnsupportedCode => {
+                write!(f, "unsupported G-code word '{}'", self.0.token)
+            }
+            ParseErrorKind::UnknownPosition => write!(
+                f,
+                "'{}' leaves out an axis whose position is not known yet",
+                self.0.token
+            ),
+            ParseErrorKind::UnknownParameter => write!(f, "unknown parameter '{}'", self.0.token),
+            ParseErrorKind::DuplicateParameter => {
+                write!(f, "parameter '{}' is given more than once", self.0.token)
+            }
+            ParseErrorKind::NotPositive => {
+                write!(f, "'{}' must be greater than zero", self.0.token)
+            }
+            ParseErrorKind::Negative => write!(f, "'{}' cannot be negative", self.0.token),
+            ParseErrorKind::MissingParameter(name) => {
+                write!(f, "'{}' is missing its {} argument", self.0.token, name)
+            }
+            ParseErrorKind::PositionalAfterNamed => write!(
+                f,
+                "positional argument '{}' follows a named argument",
+                self.0.token
+            ),
+            ParseErrorKind::BadExpression(message) => {
+                write!(f, "bad expression '{}': {}", self.0.token, message)
+            }
+            ParseErrorKind::UndefinedPose => write!(f, "pose '{}' is not defined", self.0.token),
+            ParseErrorKind::UndefinedVariable => {
+                write!(f, "variable '{}' is not defined", self.0.token)
+            }
+            ParseErrorKind::UnknownFunction => write!(f, "unknown function in '{}'", self.0.token),
+            ParseErrorKind::UndefinedSubroutine => {
+                write!(f, "subroutine '{}' is not defined", self.0.token)
+            }
+            ParseErrorKind::DuplicateDefinition => {
+                write!(f, "'{}' is already defined", self.0.token)
+            }
+            ParseErrorKind::RecursionLimit => write!(
+                f,
+                "calls to '{}' nest too deeply, is it calling itself?",
+                self.0.token
+            ),
+            ParseErrorKind::UnterminatedBlock => write!(f, "'{}' is missing its END", self.0.token),
+            ParseErrorKind::UnexpectedEnd => {
+                write!(f, "'{}' does not close any open block", self.0.token)
+            }
+            ParseErrorKind::NestedDefinition => {
+                write!(f, "subroutines cannot be defined inside a block")
+            }
+            ParseErrorKind::RunawayLoop => write!(
+                f,
+                "loop ran {} statements without producing a command",
+                crate::interpreter::MAX_IDLE_STEPS
+            ),
+            ParseErrorKind::IncludeFailed(reason) => {
+                write!(f, "cannot include '{}': {}", self.0.token, reason)
+            }
+            ParseErrorKind::ReadFailed(reason) => write!(f, "cannot read the program: {}", reason),
+            ParseErrorKind::IncludeCycle => {
+                write!(f, "'{}' is already being included", self.0.token)
+            }
+            ParseErrorKind::IncludeInBlock => {
+                write!(f, "INCLUDE is only allowed outside of blocks")
+            }
+            ParseErrorKind::UnterminatedComment => write!(f, "comment is missing its closing ')'"),
+            ParseErrorKind::BadChecksum(expected) => write!(
+                f,
+                "checksum '{}' does not match the line, expected *{}",
+                self.0.token, expected
+            ),
+            ParseErrorKind::LineNumberOrder(last) => write!(
+                f,
+                "line number '{}' does not come after N{}",
+                self.0.token, last
+            ),
+        }
+    }
+}
+
+// Every error found in a program, in source order
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseReport {
+    pub errors: Vec<ParseError>,
+}
+impl fmt::Display for ParseReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} error(s) found:", self.errors.len())?;
+
+        for error in &self.errors {
+            writeln!(f, "  {}", error)?;
+        }
+
+        Ok(())
+    }
+}
+impl std::error::Error for ParseReport {}
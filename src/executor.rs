@@ -0,0 +1,175 @@
+// 4. Locate current arm position
+// 5. Calculate deviation from target
+// 6. Generate motor commands
+
+use crate::command::Command;
+use crate::parser;
+use crate::serial::{ACK_STRING, IDLE_STRING, Link, RESEND_PREFIXES};
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+
+// Numbered lines kept for the Arduino to ask for again
+pub const RESEND_HISTORY: usize = 64;
+
+// Serial form of a command, as read by the Arduino, which is also its
+// canonical .rgcf form
+pub fn encode(command: &Command) -> String {
+    command.to_string()
+}
+
+// Line numbering of the serial stream, so the Arduino can spot a corrupted
+// or dropped line and ask for it again
+struct Numbering {
+    next: usize,
+    history: VecDeque<(usize, String)>,
+}
+
+// `N<number> text*<checksum>`
+pub fn frame(number: usize, text: &str) -> String {
+    let line = format!("N{} {}", number, text);
+    let checksum = parser::checksum(&line);
+
+    format!("{}*{}", line, checksum)
+}
+
+// Line number asked for in a `Resend: 12` or `rs 12` reply
+fn resend_request(reply: &str) -> Option<usize> {
+    let reply = reply.trim();
+
+    RESEND_PREFIXES
+        .iter()
+        .find_map(|prefix| reply.strip_prefix(prefix))
+        .and_then(|number| number.trim().trim_start_matches('N').parse().ok())
+}
+
+pub struct Executor<L: Link> {
+    link: L,
+    numbering: Option<Numbering>,
+}
+impl<L: Link> Executor<L> {
+    pub fn new(link: L) -> Executor<L> {
+        Executor {
+            link,
+            numbering: None,
+        }
+    }
+
+    // Sends every line numbered and checksummed, one at a time, waiting for
+    // the Arduino to acknowledge each before the next
+    pub fn with_line_numbers(mut self) -> Executor<L> {
+        self.numbering = Some(Numbering {
+            next: 1,
+            history: VecDeque::new(),
+        });
+        self
+    }
+
+    pub fn link(&mut self) -> &mut L {
+        &mut self.link
+    }
+
+    // A WI blocks until the Arduino reports it is idle, so nothing after it is
+    // sent while the arm is still moving
+    pub fn execute(&mut self, command: &Command) -> io::Result<()> {
+        self.send(&encode(command))?;
+
+        if *command == Command::WI {
+            self.wait_idle()?;
+        }
+
+        Ok(())
+    }
+
+    fn send(&mut self, text: &str) -> io::Result<()> {
+        let Some(numbering) = &mut self.numbering else {
+            return self.link.send(text);
+        };
+
+        let number = numbering.next;
+        let line = frame(number, text);
+
+        numbering.next += 1;
+        numbering.history.push_back((number, line.clone()));
+
+        if numbering.history.len() > RESEND_HISTORY {
+            numbering.history.pop_front();
+        }
+
+        self.link.send(&line)?;
+        self.acknowledge()
+    }
+
+    // Waits for the acknowledgement of the last line, sending lines again for
+    // as long as the Arduino asks for them. A resend request is followed by an
+    // `ok` of its own, as in Marlin, which acknowledges none of the lines. The
+    // `ok` is held back while the move queue is full, so timeouts are waited
+    // through like in `wait_idle`.
+    fn acknowledge(&mut self) -> io::Result<()> {
+        let mut pending = 1;
+        let mut resend_ok = false;
+
+        while pending > 0 {
+            let reply = match self.link.receive() {
+                Ok(reply) => reply,
+                Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+                Err(e) => return Err(e),
+            };
+
+            if reply.trim() == ACK_STRING {
+                match resend_ok {
+                    true => resend_ok = false,
+                    false => pending -= 1,
+                }
+                continue;
+            }
+
+            let Some(requested) = resend_request(&reply) else {
+                continue;
+            };
+
+            let history = &self.numbering.as_ref().unwrap().history;
+            let lines: Vec<String> = history
+                .iter()
+                .filter(|(number, _)| *number >= requested)
+                .map(|(_, line)| line.clone())
+                .collect();
+
+            if lines.is_empty() || history.front().is_some_and(|(first, _)| *first > requested) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {} is no longer held for resending", requested),
+                ));
+            }
+
+            for line in &lines {
+                self.link.send(line)?;
+            }
+
+            pending = lines.len();
+            resend_ok = true;
+        }
+
+        Ok(())
+    }
+
+    // Long moves outlast the read timeout, so timeouts are waited through.
+    // Other lines from the Arduino are skipped.
+    fn wait_idle(&mut self) -> io::Result<()> {
+        loop {
+            match self.link.receive() {
+                Ok(line) if line.trim() == IDLE_STRING => return Ok(()),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn run(&mut self, program: &[Command]) -> io::Result<()> {
+        for command in program {
+            self.execute(command)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,523 @@
+// EXPRESSIONS
+// ===========
+// Any numeric argument can be computed from variables:
+//   #safe_z = 120
+//   TG #x+10 #y [#safe_z*0.5]
+//
+// Brackets group and may contain spaces. Inside brackets, functions take
+// their arguments in brackets or parentheses, e.g. [sqrt(#a^2 + #b^2)].
+// Outside brackets `(` starts a comment. Trigonometry works in degrees.
+//
+// Comparisons (== != < <= > >=) and logic (&& || !) give 1 for true and 0
+// for false, for use in IF and WHILE conditions.
+
+use crate::error::ParseErrorKind;
+use std::collections::HashMap;
+use std::ops::Range;
+
+pub type Variables = HashMap<String, f32>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+// Spans are character offsets into the expression text
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f32),
+    Variable(String, Range<usize>),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>, Range<usize>),
+    Call(String, Vec<Expr>, Range<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprError {
+    pub span: Range<usize>,
+    pub kind: ParseErrorKind,
+}
+
+// Name and argument count of every function that can be called
+pub const FUNCTIONS: &[(&str, usize)] = &[
+    ("sin", 1),
+    ("cos", 1),
+    ("tan", 1),
+    ("asin", 1),
+    ("acos", 1),
+    ("atan", 1),
+    ("atan2", 2),
+    ("sqrt", 1),
+    ("abs", 1),
+    ("exp", 1),
+    ("ln", 1),
+    ("log", 1),
+    ("floor", 1),
+    ("ceil", 1),
+    ("round", 1),
+    ("min", 2),
+    ("max", 2),
+    ("pow", 2),
+    ("pi", 0),
+];
+
+// True when an argument has to go through the expression parser rather than
+// being read as a plain number
+pub fn is_expression(text: &str) -> bool {
+    text.contains([
+        '#', '[', '+', '-', '*', '/', '^', '%', '<', '>', '=', '!', '&', '|',
+    ]) && text.parse::<f32>().is_err()
+}
+
+pub fn is_identifier(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || character == '_')
+}
+
+struct ExprParser {
+    chars: Vec<char>,
+    position: usize,
+}
+impl ExprParser {
+    fn peek(&mut self) -> Option<char> {
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.position += 1;
+        }
+
+        self.chars.get(self.position).copied()
+    }
+
+    // Position of the next non-whitespace character
+    fn start(&mut self) -> usize {
+        self.peek();
+        self.position
+    }
+
+    fn error(&self, span: Range<usize>, message: &str) -> ExprError {
+        ExprError {
+            span,
+            kind: ParseErrorKind::BadExpression(message.to_string()),
+        }
+    }
+
+    fn expect(&mut self, closing: char) -> Result<(), ExprError> {
+        if self.peek() == Some(closing) {
+            self.position += 1;
+            Ok(())
+        } else {
+            let message = format!("expected '{}'", closing);
+            Err(self.error(self.position..self.position + 1, &message))
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.position;
+
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+        {
+            self.position += 1;
+        }
+
+        self.chars[start..self.position].iter().collect()
+    }
+
+    // Consumes `operator` if it comes next
+    fn eat(&mut self, operator: &str) -> bool {
+        self.peek();
+
+        let matches = operator
+            .chars()
+            .enumerate()
+            .all(|(index, c)| self.chars.get(self.position + index) == Some(&c));
+
+        if matches {
+            self.position += operator.chars().count();
+        }
+
+        matches
+    }
+
+    // or := and ('||' and)*
+    fn or(&mut self) -> Result<Expr, ExprError> {
+        let start = self.start();
+        let mut left = self.and()?;
+
+        while self.eat("||") {
+            let right = self.and()?;
+            left = Expr::Binary(
+                BinaryOp::Or,
+                Box::new(left),
+                Box::new(right),
+                start..self.position,
+            );
+        }
+
+        Ok(left)
+    }
+
+    // and := comparison ('&&' comparison)*
+    fn and(&mut self) -> Result<Expr, ExprError> {
+        let start = self.start();
+        let mut left = self.comparison()?;
+
+        while self.eat("&&") {
+            let right = self.comparison()?;
+            left = Expr::Binary(
+                BinaryOp::And,
+                Box::new(left),
+                Box::new(right),
+                start..self.position,
+            );
+        }
+
+        Ok(left)
+    }
+
+    // comparison := sum (('==' | '!=' | '<=' | '>=' | '<' | '>') sum)?
+    fn comparison(&mut self) -> Result<Expr, ExprError> {
+        let start = self.start();
+        let left = self.sum()?;
+
+        let operators = [
+            ("==", BinaryOp::Equal),
+            ("!=", BinaryOp::NotEqual),
+            ("<=", BinaryOp::LessEqual),
+            (">=", BinaryOp::GreaterEqual),
+            ("<", BinaryOp::Less),
+            (">", BinaryOp::Greater),
+        ];
+
+        for (operator, op) in operators {
+            if self.eat(operator) {
+                let right = self.sum()?;
+                return Ok(Expr::Binary(
+                    op,
+                    Box::new(left),
+                    Box::new(right),
+                    start..self.position,
+                ));
+            }
+        }
+
+        Ok(left)
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        let start = self.start();
+        let mut left = self.product()?;
+
+        loop {
+            let op = match self.peek() {
+                Some('+') => BinaryOp::Add,
+                Some('-') => BinaryOp::Subtract,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+
+            let right = self.product()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right), start..self.position);
+        }
+    }
+
+    // product := unary (('*' | '/' | '%') unary)*
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let start = self.start();
+        let mut left = self.unary()?;
+
+        loop {
+            let op = match self.peek() {
+                Some('*') => BinaryOp::Multiply,
+                Some('/') => BinaryOp::Divide,
+                Some('%') => BinaryOp::Remainder,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+
+            let right = self.unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right), start..self.position);
+        }
+    }
+
+    // unary := ('-' | '+' | '!') unary | power
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        match self.peek() {
+            Some('!') if self.chars.get(self.position + 1) != Some(&'=') => {
+                self.position += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some('-') => {
+                self.position += 1;
+                Ok(Expr::Negate(Box::new(self.unary()?)))
+            }
+            Some('+') => {
+                self.position += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    // power := atom ('^' unary)?
+    fn power(&mut self) -> Result<Expr, ExprError> {
+        let start = self.start();
+        let base = self.atom()?;
+
+        if self.peek() != Some('^') {
+            return Ok(base);
+        }
+        self.position += 1;
+
+        let exponent = self.unary()?;
+        Ok(Expr::Binary(
+            BinaryOp::Power,
+            Box::new(base),
+            Box::new(exponent),
+            start..self.position,
+        ))
+    }
+
+    fn atom(&mut self) -> Result<Expr, ExprError> {
+        let start = self.start();
+
+        match self.peek() {
+            Some('[') | Some('(') => {
+                let closing = if self.chars[self.position] == '[' {
+                    ']'
+                } else {
+                    ')'
+                };
+                self.position += 1;
+
+                let inner = self.or()?;
+                self.expect(closing)?;
+                Ok(inner)
+            }
+            Some('#') => {
+                self.position += 1;
+                let name = self.identifier();
+
+                if name.is_empty() {
+                    return Err(self.error(start..self.position, "expected a variable name"));
+                }
+
+                Ok(Expr::Variable(name.to_lowercase(), start..self.position))
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                while self
+                    .chars
+                    .get(self.position)
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    self.position += 1;
+                }
+
+                // `1e3`, `2.5E-4`, only when digits follow so `e` is not
+                // taken from a name
+                if let Some('e' | 'E') = self.chars.get(self.position) {
+                    let sign = matches!(self.chars.get(self.position + 1), Some('+' | '-'));
+                    let digits = self.position + 1 + sign as usize;
+
+                    if self.chars.get(digits).is_some_and(|c| c.is_ascii_digit()) {
+                        self.position = digits;
+                        while self
+                            .chars
+                            .get(self.position)
+                            .is_some_and(|c| c.is_ascii_digit())
+                        {
+                            self.position += 1;
+                        }
+                    }
+                }
+
+                let text: String = self.chars[start..self.position].iter().collect();
+                text.parse::<f32>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .map(Expr::Number)
+                    .ok_or(ExprError {
+                        span: start..self.position,
+                        kind: ParseErrorKind::BadNumber,
+                    })
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.identifier().to_lowercase();
+                let mut arguments = Vec::new();
+
+                if let Some(opening @ ('[' | '(')) = self.peek() {
+                    let closing = if opening == '[' { ']' } else { ')' };
+                    self.position += 1;
+
+                    if self.peek() != Some(closing) {
+                        arguments.push(self.or()?);
+
+                        while self.peek() == Some(',') {
+                            self.position += 1;
+                            arguments.push(self.or()?);
+                        }
+                    }
+
+                    self.expect(closing)?;
+                }
+
+                Ok(Expr::Call(name, arguments, start..self.position))
+            }
+            Some(_) => Err(self.error(start..start + 1, "unexpected character")),
+            None => Err(self.error(start..start + 1, "expression ends early")),
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Expr, ExprError> {
+    let mut parser = ExprParser {
+        chars: text.chars().collect(),
+        position: 0,
+    };
+
+    let expr = parser.or()?;
+
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => {
+            let end = parser.chars.len();
+            Err(parser.error(parser.position..end, "unexpected trailing input"))
+        }
+    }
+}
+
+fn call(name: &str, arguments: &[f32], span: Range<usize>) -> Result<f32, ExprError> {
+    let Some((_, count)) = FUNCTIONS.iter().find(|(function, _)| *function == name) else {
+        return Err(ExprError {
+            span,
+            kind: ParseErrorKind::UnknownFunction,
+        });
+    };
+
+    if arguments.len() != *count {
+        return Err(ExprError {
+            span,
+            kind: ParseErrorKind::BadExpression(format!(
+                "{} takes {} argument(s), found {}",
+                name,
+                count,
+                arguments.len()
+            )),
+        });
+    }
+
+    let a = arguments.first().copied().unwrap_or(0.0);
+    let b = arguments.get(1).copied().unwrap_or(0.0);
+
+    Ok(match name {
+        "sin" => a.to_radians().sin(),
+        "cos" => a.to_radians().cos(),
+        "tan" => a.to_radians().tan(),
+        "asin" => a.asin().to_degrees(),
+        "acos" => a.acos().to_degrees(),
+        "atan" => a.atan().to_degrees(),
+        "atan2" => a.atan2(b).to_degrees(),
+        "sqrt" => a.sqrt(),
+        "abs" => a.abs(),
+        "exp" => a.exp(),
+        "ln" => a.ln(),
+        "log" => a.log10(),
+        "floor" => a.floor(),
+        "ceil" => a.ceil(),
+        "round" => a.round(),
+        "min" => a.min(b),
+        "max" => a.max(b),
+        "pow" => a.powf(b),
+        _ => std::f32::consts::PI,
+    })
+}
+
+pub fn evaluate(expr: &Expr, variables: &Variables) -> Result<f32, ExprError> {
+    match expr {
+        Expr::Number(value) => Ok(*value),
+        Expr::Variable(name, span) => variables.get(name).copied().ok_or(ExprError {
+            span: span.clone(),
+            kind: ParseErrorKind::UndefinedVariable,
+        }),
+        Expr::Negate(inner) => Ok(-evaluate(inner, variables)?),
+        Expr::Not(inner) => Ok(truth(evaluate(inner, variables)? == 0.0)),
+        Expr::Binary(op, left, right, span) => {
+            let left = evaluate(left, variables)?;
+            let right = evaluate(right, variables)?;
+
+            if right == 0.0 && matches!(op, BinaryOp::Divide | BinaryOp::Remainder) {
+                return Err(ExprError {
+                    span: span.clone(),
+                    kind: ParseErrorKind::BadExpression("division by zero".to_string()),
+                });
+            }
+
+            let value = match op {
+                BinaryOp::Add => left + right,
+                BinaryOp::Subtract => left - right,
+                BinaryOp::Multiply => left * right,
+                BinaryOp::Divide => left / right,
+                BinaryOp::Remainder => left % right,
+                BinaryOp::Power => left.powf(right),
+                BinaryOp::Equal => truth((left - right).abs() < EPSILON),
+                BinaryOp::NotEqual => truth((left - right).abs() >= EPSILON),
+                BinaryOp::Less => truth(left < right),
+                BinaryOp::LessEqual => truth(left <= right),
+                BinaryOp::Greater => truth(left > right),
+                BinaryOp::GreaterEqual => truth(left >= right),
+                BinaryOp::And => truth(left != 0.0 && right != 0.0),
+                BinaryOp::Or => truth(left != 0.0 || right != 0.0),
+            };
+
+            finite(value, span)
+        }
+        Expr::Call(name, arguments, span) => {
+            let values = arguments
+                .iter()
+                .map(|argument| evaluate(argument, variables))
+                .collect::<Result<Vec<f32>, ExprError>>()?;
+
+            finite(call(name, &values, span.clone())?, span)
+        }
+    }
+}
+
+// Values closer than this compare equal
+pub const EPSILON: f32 = 1e-6;
+
+fn truth(condition: bool) -> f32 {
+    if condition { 1.0 } else { 0.0 }
+}
+
+fn finite(value: f32, span: &Range<usize>) -> Result<f32, ExprError> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(ExprError {
+            span: span.clone(),
+            kind: ParseErrorKind::BadExpression("result is not a finite number".to_string()),
+        })
+    }
+}
@@ -0,0 +1,120 @@
+// FORMATTER
+// =========
+// Rewrites .rgcf text in canonical form: one space between terms, two spaces
+// of indentation per block, numbers in their shortest form. Comments are kept
+// where they are, and the program parses into the same commands as before.
+//
+// Lines that cannot be formatted safely are left as they are: G-code sections,
+// lines with a line number (their checksum covers the exact text) and lines
+// with an unterminated comment.
+
+use crate::ast::{self, Line, Piece};
+use crate::expr;
+use crate::parser::Dialect;
+
+const INDENT: &str = "  ";
+
+// Keywords whose rest of line is read from the raw text
+const RAW_HEADERS: &[&str] = &["SUB", "INCLUDE"];
+
+// Shortest form of a plain number, e.g. `010.50` to `10.5`
+fn number(text: &str) -> String {
+    let numeric = text.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c));
+
+    match text.parse::<f32>() {
+        Ok(value) if numeric => value.to_string(),
+        _ => text.to_string(),
+    }
+}
+
+// An argument, with the value of a `NAME=value` one formatted
+fn argument(text: &str) -> String {
+    match text.split_once('=') {
+        Some((key, value)) if expr::is_identifier(key) => {
+            format!("{}={}", key, number(value))
+        }
+        _ => number(text),
+    }
+}
+
+// Formats the code of a line, without indentation. None when the line is
+// left as it is.
+fn format_code(line: &Line) -> Option<String> {
+    if line.unterminated || line.line_number.is_some() {
+        return None;
+    }
+
+    let mut code = String::new();
+
+    if let Some(keyword) = line.keyword().filter(|first| RAW_HEADERS.contains(first)) {
+        let header = line.source.split(';').next().unwrap_or("").trim();
+        code.push_str(keyword);
+        code.push(' ');
+        code.push_str(header.strip_prefix(keyword)?.trim_start());
+    } else {
+        let mut terms = 0;
+
+        for piece in &line.pieces {
+            let (text, attached) = match piece {
+                Piece::Term(term) if terms == 0 => (term.text.clone(), false),
+                Piece::Term(term) => (argument(&term.text), false),
+                Piece::Comment(comment) => (comment.text.clone(), comment.attached),
+            };
+
+            if !code.is_empty() && !attached {
+                code.push(' ');
+            }
+            code.push_str(&text);
+
+            if matches!(piece, Piece::Term(_)) {
+                terms += 1;
+            }
+        }
+    }
+
+    if let Some(trailing) = &line.trailing {
+        if !code.is_empty() {
+            code.push(' ');
+        }
+        code.push_str(&trailing.text);
+    }
+
+    Some(code.trim_end().to_string())
+}
+
+// Formats a whole program, which ends with a newline
+pub fn format_program(source: &str, dialect: Dialect) -> String {
+    let mut depth: usize = 0;
+    let mut output = String::new();
+
+    for line in ast::parse(source, dialect).lines() {
+        let code = match line.dialect {
+            Dialect::Rgcf if !line.source.trim().starts_with('%') => format_code(line),
+            _ => None,
+        };
+
+        let Some(code) = code else {
+            output.push_str(line.source.trim_end());
+            output.push('\n');
+            continue;
+        };
+
+        let first = code.split_whitespace().next().unwrap_or("");
+
+        if matches!(first, "END" | "ELSE") {
+            depth = depth.saturating_sub(1);
+        }
+
+        if !code.is_empty() {
+            output.push_str(&INDENT.repeat(depth));
+            output.push_str(&code);
+        }
+        output.push('\n');
+
+        if matches!(first, "SUB" | "REPEAT" | "WHILE" | "IF" | "ELSE") {
+            depth += 1;
+        }
+    }
+
+    output
+}
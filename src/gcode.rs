@@ -0,0 +1,213 @@
+// STANDARD G-CODE
+// ===============
+// Word addressed G-code as emitted by CAM and path tools, lowered into the
+// same commands as .rgcf programs.
+//
+// G0/G1 X Y Z   Move to target (TG), left out axes keep their last value
+// G4 P(ms)/S(s) Dwell (DW)
+// G28           Return home (RH)
+// G90/G91       Absolute/relative coordinates
+// G20/G21       Inches/millimetres, shared with the UN setting of .rgcf
+// G17-G19, G40, G49, G54-G59, G80, G94
+//               Plane, cancels, work offsets and feed mode from CAM preambles,
+//               which mean nothing to the arm and are ignored
+// F             Feed rate (FR), modal
+// M3/M4 S       Close claw (CL), S sets the grip force
+// M5            Open claw (CL)
+// M2/M30        Program end, nothing after it is run
+// M112          Force stop (FS)
+// M400          Wait until motion is complete (WI)
+
+use crate::command::{CLAW_ACCEL, CLAW_FORCE, CLAW_HOLD, CLAW_OPEN, CLAW_SPEED, Command};
+use crate::error::{ParseError, ParseErrorKind};
+use crate::motion::Motion;
+use crate::parser::{self, Token};
+use crate::position::Position;
+use crate::units::{LengthUnit, Unit, Units};
+
+pub const EXTENSIONS: &[&str] = &["gcode", "gco", "g", "nc", "ngc"];
+
+// A letter and its number, e.g. `X10.5`
+struct Word<'a> {
+    letter: char,
+    value: f32,
+    token: Token<'a>,
+}
+
+// Splits a token into words, so both `G1 X10` and `G1X10` are accepted
+fn words<'a>(line: usize, token: &Token<'a>) -> Result<Vec<Word<'a>>, ParseError> {
+    let mut words = Vec::new();
+    let mut starts: Vec<usize> = token
+        .text
+        .char_indices()
+        .filter(|(_, character)| character.is_ascii_alphabetic())
+        .map(|(offset, _)| offset)
+        .collect();
+
+    if starts.first() != Some(&0) {
+        return Err(ParseError::new(
+            line,
+            token,
+            ParseErrorKind::UnsupportedCode,
+        ));
+    }
+
+    starts.push(token.text.len());
+
+    for pair in starts.windows(2) {
+        let text = &token.text[pair[0]..pair[1]];
+        let word = Token {
+            text,
+            column: token.column + token.text[..pair[0]].chars().count(),
+        };
+        let letter = text.chars().next().unwrap().to_ascii_uppercase();
+        let value = text[1..]
+            .parse::<f32>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| ParseError::new(line, &word, ParseErrorKind::BadNumber))?;
+
+        words.push(Word {
+            letter,
+            value,
+            token: word,
+        });
+    }
+
+    Ok(words)
+}
+
+// A line can hold several codes, so it lowers into any number of commands.
+// The modal state lives in the parser and is shared with .rgcf lines;
+// positions are tracked in millimetres whatever `units` says. `ended` is set
+// by a program end, the codes after it on the line being dropped.
+pub fn parse_line(
+    line: usize,
+    source: &str,
+    units: &mut Units,
+    position: &mut Position,
+    motion: &Motion,
+    ended: &mut bool,
+) -> Result<Vec<Command>, ParseError> {
+    let mut codes: Vec<Word> = Vec::new();
+    let mut parameters: Vec<Word> = Vec::new();
+
+    for token in parser::tokenize(line, source)? {
+        for word in words(line, &token)? {
+            match word.letter {
+                'G' | 'M' => codes.push(word),
+                'X' | 'Y' | 'Z' | 'F' | 'P' | 'S' => parameters.push(word),
+                _ => {
+                    return Err(ParseError::new(
+                        line,
+                        &word.token,
+                        ParseErrorKind::UnsupportedCode,
+                    ));
+                }
+            }
+        }
+    }
+
+    let parameter = |letter: char| {
+        parameters
+            .iter()
+            .find(|word| word.letter == letter)
+            .map(|word| word.value)
+    };
+
+    // Modal codes apply to the whole line, whatever order they appear in
+    for code in &codes {
+        match (code.letter, code.value) {
+            ('G', 90.0) => position.relative = false,
+            ('G', 91.0) => position.relative = true,
+            ('G', 20.0) => units.set(Unit::Length(LengthUnit::Inch)),
+            ('G', 21.0) => units.set(Unit::Length(LengthUnit::Millimetre)),
+            _ => {}
+        }
+    }
+
+    let mut commands = Vec::new();
+
+    if let Some(feed) = parameter('F').map(|feed| units.length(feed)) {
+        if feed <= 0.0 {
+            let token = &parameters
+                .iter()
+                .find(|word| word.letter == 'F')
+                .unwrap()
+                .token;
+            return Err(ParseError::new(line, token, ParseErrorKind::NotPositive));
+        }
+
+        if feed != motion.feed {
+            commands.push(Command::FR(feed));
+        }
+    }
+
+    for code in &codes {
+        match (code.letter, code.value) {
+            ('G', 0.0) | ('G', 1.0) => {
+                let values = ['X', 'Y', 'Z']
+                    .map(|letter| parameter(letter).map(|value| units.length(value)));
+
+                let Some(command) = position.resolve(values, position.relative) else {
+                    return Err(ParseError::new(
+                        line,
+                        &code.token,
+                        ParseErrorKind::UnknownPosition,
+                    ));
+                };
+
+                position.track(&command);
+                commands.push(command);
+            }
+            ('G', 4.0) => {
+                let milliseconds = parameter('P')
+                    .or(parameter('S').map(|seconds| seconds * 1000.0))
+                    .unwrap_or(0.0);
+
+                if milliseconds < 0.0 {
+                    let token = &parameters
+                        .iter()
+                        .find(|word| word.letter == 'P' || word.letter == 'S')
+                        .unwrap()
+                        .token;
+                    return Err(ParseError::new(line, token, ParseErrorKind::Negative));
+                }
+
+                commands.push(Command::DW(milliseconds));
+            }
+            ('G', 28.0) => {
+                position.track(&Command::RH);
+                commands.push(Command::RH);
+            }
+            ('G', 20.0) | ('G', 21.0) | ('G', 90.0) | ('G', 91.0) => {}
+            ('G', 17.0..=19.0 | 40.0 | 49.0 | 54.0..=59.0 | 80.0 | 94.0)
+                if code.value.fract() == 0.0 => {}
+            ('M', 3.0) | ('M', 4.0) => commands.push(Command::CL(
+                0.0,
+                parameter('S').unwrap_or(CLAW_FORCE),
+                CLAW_SPEED,
+                CLAW_ACCEL,
+                CLAW_HOLD,
+            )),
+            ('M', 5.0) => commands.push(Command::CL(
+                CLAW_OPEN, CLAW_FORCE, CLAW_SPEED, CLAW_ACCEL, CLAW_HOLD,
+            )),
+            ('M', 2.0) | ('M', 30.0) => {
+                *ended = true;
+                break;
+            }
+            ('M', 112.0) => commands.push(Command::FS),
+            ('M', 400.0) => commands.push(Command::WI),
+            _ => {
+                return Err(ParseError::new(
+                    line,
+                    &code.token,
+                    ParseErrorKind::UnsupportedCode,
+                ));
+            }
+        }
+    }
+
+    Ok(commands)
+}
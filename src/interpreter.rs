@@ -0,0 +1,909 @@
+// INTERPRETER
+// ===========
+// Runs a program's control flow and hands out commands one at a time, so a
+// program that loops forever can be streamed without being expanded first.
+//
+// SUB name(a, b) ... END    Subroutine, defined before it is CALLed
+// CALL name 1 2             Runs a subroutine with its parameters bound
+// REPEAT n ... END          Runs the body n times
+// WHILE cond ... END        Runs the body while cond is non-zero
+// IF cond ... ELSE ... END  Runs one branch, ELSE is optional
+// INCLUDE "path.rgcf"       Runs another file in place, outside of blocks
+//
+// Blocks are read in full before they run, top-level lines run as soon as
+// they are read. An error abandons the top-level statement it happened in and
+// the program carries on with the next one.
+//
+// Included paths are relative to the file holding the INCLUDE line. A block
+// has to end in the file it starts in. The dialect, units and positioning
+// mode an included file switches to last until it ends.
+
+use crate::command::Command;
+use crate::error::{Include, Origin, ParseError, ParseErrorKind, ParseReport};
+use crate::expr::{self, Variables};
+use crate::parser::{self, Dialect, LineNumbers, Parser, Token};
+use crate::poses::Poses;
+use crate::units::Units;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{self, BufRead};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Deepest CALL nesting before a program is assumed to recurse forever
+pub const MAX_CALL_DEPTH: usize = 64;
+
+// Statements run without producing a command before a loop is assumed to
+// spin forever
+pub const MAX_IDLE_STEPS: usize = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subroutine {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub line: usize,
+    // Of the SUB keyword, for errors about the definition
+    pub columns: Range<usize>,
+    pub body: Rc<[Statement]>,
+}
+
+// Lines keep their source so they are lowered with the variables in effect
+// when they run
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Line(usize, String),
+    Call(usize, String),
+    Sub(Rc<Subroutine>),
+    Repeat {
+        line: usize,
+        source: String,
+        body: Rc<[Statement]>,
+    },
+    While {
+        line: usize,
+        source: String,
+        body: Rc<[Statement]>,
+    },
+    If {
+        line: usize,
+        source: String,
+        then: Rc<[Statement]>,
+        otherwise: Rc<[Statement]>,
+    },
+}
+
+const KEYWORDS: &[&str] = &[
+    "SUB", "CALL", "REPEAT", "WHILE", "IF", "ELSE", "END", "INCLUDE",
+];
+
+// First token of a line when it is one of the block keywords. Lines that do
+// not tokenize are left for the parser to report.
+fn keyword(line: usize, source: &str) -> Option<(&'static str, Token<'_>)> {
+    let terms = parser::tokenize(line, source).ok()?;
+    let first = *terms.first()?;
+
+    KEYWORDS
+        .iter()
+        .find(|keyword| **keyword == first.text)
+        .map(|keyword| (*keyword, first))
+}
+
+// Reads `name(a, b)` from the text following `SUB`. The parameter list sits
+// in what the tokenizer treats as a comment, so it is read from the source.
+fn subroutine_header(
+    line: usize,
+    source: &str,
+    keyword: &Token,
+) -> Result<(String, Vec<String>), ParseError> {
+    let header = source.split(';').next().unwrap_or("");
+    let rest: String = header.chars().skip(keyword.columns().end - 1).collect();
+    let bad_header = |message: &str| {
+        ParseError::new(
+            line,
+            keyword,
+            ParseErrorKind::BadExpression(message.to_string()),
+        )
+    };
+
+    let (name, parameters) = match rest.split_once('(') {
+        Some((name, parameters)) => {
+            let Some((parameters, _)) = parameters.split_once(')') else {
+                return Err(bad_header("parameter list is missing its closing ')'"));
+            };
+            (name.trim(), parameters)
+        }
+        None => (rest.trim(), ""),
+    };
+
+    if !expr::is_identifier(name) {
+        return Err(bad_header("expected a subroutine name"));
+    }
+
+    let parameters = parameters
+        .split(',')
+        .map(|parameter| parameter.trim().trim_start_matches('#').to_lowercase())
+        .filter(|parameter| !parameter.is_empty())
+        .collect::<Vec<String>>();
+
+    if let Some(parameter) = parameters.iter().find(|p| !expr::is_identifier(p)) {
+        return Err(bad_header(&format!(
+            "invalid parameter name '{}'",
+            parameter
+        )));
+    }
+
+    Ok((name.to_lowercase(), parameters))
+}
+
+// How a block was closed
+enum Closer {
+    End,
+    Else,
+}
+
+// Reads statements up to the END (or ELSE) closing the block opened on
+// `opener`. Errors in nested blocks are kept until the block is closed so the
+// lines after them are not mistaken for top-level ones.
+fn read_block<I: Iterator<Item = (usize, String)>>(
+    lines: &mut I,
+    numbers: &mut LineNumbers,
+    opener: (usize, &Token),
+    allow_else: bool,
+) -> Result<(Vec<Statement>, Closer), ParseError> {
+    let mut body = Vec::new();
+    let mut first_error: Option<ParseError> = None;
+
+    loop {
+        let Some((line, source)) = lines.next() else {
+            return Err(ParseError::new(
+                opener.0,
+                opener.1,
+                ParseErrorKind::UnterminatedBlock,
+            ));
+        };
+
+        let source = match numbers.strip(line, &source) {
+            Ok(source) => source,
+            Err(e) => {
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+
+        let closer = match keyword(line, &source) {
+            Some(("END", _)) => Some(Closer::End),
+            Some(("ELSE", _)) if allow_else => Some(Closer::Else),
+            Some(("SUB", token)) => {
+                first_error.get_or_insert(ParseError::new(
+                    line,
+                    &token,
+                    ParseErrorKind::NestedDefinition,
+                ));
+                // Still read the nested body so its END does not close this block
+                let _ = read_block(lines, numbers, (line, &token), false);
+                continue;
+            }
+            _ => None,
+        };
+
+        if let Some(closer) = closer {
+            return match first_error {
+                Some(e) => Err(e),
+                None => Ok((body, closer)),
+            };
+        }
+
+        match read_statement(lines, numbers, line, source) {
+            Ok(statement) => body.push(statement),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+}
+
+// Builds the statement starting on `line`, reading the rest of its block
+// from `lines` when it opens one
+fn read_statement<I: Iterator<Item = (usize, String)>>(
+    lines: &mut I,
+    numbers: &mut LineNumbers,
+    line: usize,
+    source: String,
+) -> Result<Statement, ParseError> {
+    let Some((keyword, token)) = keyword(line, &source) else {
+        return Ok(Statement::Line(line, source));
+    };
+    let token = Token {
+        text: keyword,
+        column: token.column,
+    };
+
+    match keyword {
+        "CALL" => Ok(Statement::Call(line, source)),
+        "END" | "ELSE" => Err(ParseError::new(line, &token, ParseErrorKind::UnexpectedEnd)),
+        "INCLUDE" => Err(ParseError::new(
+            line,
+            &token,
+            ParseErrorKind::IncludeInBlock,
+        )),
+        "SUB" => {
+            let header = subroutine_header(line, &source, &token);
+            let (body, _) = read_block(lines, numbers, (line, &token), false)?;
+            let (name, parameters) = header?;
+
+            Ok(Statement::Sub(Rc::new(Subroutine {
+                name,
+                parameters,
+                line,
+                columns: token.columns(),
+                body: body.into(),
+            })))
+        }
+        "REPEAT" | "WHILE" => {
+            let (body, _) = read_block(lines, numbers, (line, &token), false)?;
+            let body: Rc<[Statement]> = body.into();
+
+            Ok(match keyword {
+                "REPEAT" => Statement::Repeat { line, source, body },
+                _ => Statement::While { line, source, body },
+            })
+        }
+        _ => {
+            let (then, closer) = read_block(lines, numbers, (line, &token), true)?;
+            let otherwise = match closer {
+                Closer::Else => read_block(lines, numbers, (line, &token), false)?.0,
+                Closer::End => Vec::new(),
+            };
+
+            Ok(Statement::If {
+                line,
+                source,
+                then: then.into(),
+                otherwise: otherwise.into(),
+            })
+        }
+    }
+}
+
+enum FrameKind {
+    Block,
+    Repeat { remaining: u64 },
+    While { line: usize, source: String },
+    Call { saved: Variables, dialect: Dialect },
+}
+
+// A block being run, and how far into it the program is. `line` is where
+// the block was entered from, `origin` the file its body was read from.
+struct Frame {
+    body: Rc<[Statement]>,
+    index: usize,
+    line: usize,
+    origin: Rc<Origin>,
+    kind: FrameKind,
+}
+
+// A file pulled in by INCLUDE, read up front. `path` is canonical for cycle
+// checks. The modes of the including file are restored once it runs out.
+struct IncludedFile {
+    lines: std::vec::IntoIter<(usize, String)>,
+    numbers: LineNumbers,
+    path: PathBuf,
+    origin: Rc<Origin>,
+    dialect: Dialect,
+    units: Units,
+    relative: bool,
+}
+
+// Reads the path following `INCLUDE`. Quotes are optional, and the path is
+// read from the source since the tokenizer would split it on spaces and
+// parentheses.
+fn include_path<'a>(source: &'a str, keyword: &Token) -> Token<'a> {
+    let header = source.split(';').next().unwrap_or("");
+    let start = header
+        .char_indices()
+        .nth(keyword.columns().end - 1)
+        .map(|(index, _)| index)
+        .unwrap_or(header.len());
+    let rest = &header[start..];
+    let path = rest.trim_start();
+    let column = keyword.columns().end + rest[..rest.len() - path.len()].chars().count();
+
+    Token {
+        text: path.trim_end().trim_matches('"'),
+        column,
+    }
+}
+
+// Where the lines of a program come from, each with its 1-based number. A
+// source that can fail to read reports why, and on which line, once it runs
+// out of lines.
+pub trait Lines: Iterator<Item = (usize, String)> {
+    fn error(&mut self) -> Option<(usize, io::Error)> {
+        None
+    }
+}
+
+pub struct Interpreter<I: Lines> {
+    lines: I,
+    numbers: LineNumbers,
+    parser: Parser,
+    subroutines: HashMap<String, (Rc<Subroutine>, Rc<Origin>)>,
+    frames: Vec<Frame>,
+    // Commands lowered but not handed out yet, with the line they came from
+    pending: VecDeque<(Command, usize, Rc<Origin>)>,
+    location: Option<(usize, Rc<Origin>)>,
+    root: Rc<Origin>,
+    root_path: Option<PathBuf>,
+    files: Vec<IncludedFile>,
+}
+// Numbered lines of a program held in memory
+pub struct SourceLines<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+}
+impl<'a> Iterator for SourceLines<'a> {
+    type Item = (usize, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines
+            .next()
+            .map(|(line_idx, line)| (line_idx + 1, line.to_string()))
+    }
+}
+impl Lines for SourceLines<'_> {}
+
+// Numbered lines read one at a time from a file, stdin or a socket, so only
+// the line being run (or the block it belongs to) is held in memory
+pub struct ReaderLines<R: BufRead> {
+    reader: R,
+    line: usize,
+    error: Option<io::Error>,
+    done: bool,
+}
+impl<R: BufRead> Iterator for ReaderLines<R> {
+    type Item = (usize, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut source = String::new();
+
+        match self.reader.read_line(&mut source) {
+            Ok(0) => {
+                self.done = true;
+                None
+            }
+            Ok(_) => {
+                self.line += 1;
+
+                if source.ends_with('\n') {
+                    source.pop();
+                    if source.ends_with('\r') {
+                        source.pop();
+                    }
+                }
+
+                Some((self.line, source))
+            }
+            Err(e) => {
+                self.done = true;
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+}
+impl<R: BufRead> Lines for ReaderLines<R> {
+    fn error(&mut self) -> Option<(usize, io::Error)> {
+        self.error.take().map(|e| (self.line + 1, e))
+    }
+}
+impl<R: BufRead> Interpreter<ReaderLines<R>> {
+    pub fn from_reader(reader: R, dialect: Dialect) -> Interpreter<ReaderLines<R>> {
+        let lines = ReaderLines {
+            reader,
+            line: 0,
+            error: None,
+            done: false,
+        };
+
+        Interpreter::new(lines, dialect)
+    }
+}
+impl<'a> Interpreter<SourceLines<'a>> {
+    pub fn from_source(source: &'a str, dialect: Dialect) -> Interpreter<SourceLines<'a>> {
+        let lines = SourceLines {
+            lines: source.lines().enumerate(),
+        };
+
+        Interpreter::new(lines, dialect)
+    }
+}
+impl<I: Lines> Interpreter<I> {
+    // `lines` yields each line with its 1-based line number
+    pub fn new(lines: I, dialect: Dialect) -> Interpreter<I> {
+        Interpreter {
+            lines,
+            numbers: LineNumbers::default(),
+            parser: Parser::new(dialect),
+            subroutines: HashMap::new(),
+            frames: Vec::new(),
+            pending: VecDeque::new(),
+            location: None,
+            root: Rc::new(Origin::default()),
+            root_path: None,
+            files: Vec::new(),
+        }
+    }
+
+    // Names the file the program was read from, so errors point into it and
+    // INCLUDE paths resolve next to it
+    pub fn with_path(mut self, path: &Path) -> Interpreter<I> {
+        self.root = Rc::new(Origin {
+            file: Some(path.to_path_buf()),
+            included_from: Vec::new(),
+        });
+        self.root_path = path.canonicalize().ok();
+        self
+    }
+
+    // Variables can be set between commands to feed runtime inputs into
+    // IF and WHILE conditions
+    pub fn set_variable(&mut self, name: &str, value: f32) {
+        self.parser
+            .variables
+            .insert(name.trim_start_matches('#').to_lowercase(), value);
+    }
+
+    pub fn variable(&self, name: &str) -> Option<f32> {
+        self.parser
+            .variables
+            .get(&name.trim_start_matches('#').to_lowercase())
+            .copied()
+    }
+
+    // Poses loaded before the program starts, e.g. from a pose file
+    pub fn with_poses(mut self, poses: Poses) -> Interpreter<I> {
+        self.parser.poses = poses;
+        self
+    }
+
+    pub fn poses(&self) -> &Poses {
+        &self.parser.poses
+    }
+
+    // Line the last command handed out was lowered from, and its file
+    pub fn location(&self) -> Option<(usize, &Origin)> {
+        self.location
+            .as_ref()
+            .map(|(line, origin)| (*line, origin.as_ref()))
+    }
+
+    pub fn subroutine(&self, name: &str) -> Option<&Subroutine> {
+        self.subroutines
+            .get(&name.to_lowercase())
+            .map(|(subroutine, _)| subroutine.as_ref())
+    }
+
+    // Evaluates the expression following the keyword of a block line
+    fn condition(&self, line: usize, source: &str) -> Result<f32, ParseError> {
+        let terms = parser::tokenize(line, source)?;
+
+        if terms.len() < 2 {
+            return Err(parser::arity_error(line, terms[0].text, &terms[0], &[], 1));
+        }
+
+        self.parser.evaluate(line, &terms[1..])
+    }
+
+    fn push(&mut self, line: usize, body: &Rc<[Statement]>, origin: &Rc<Origin>, kind: FrameKind) {
+        self.frames.push(Frame {
+            body: body.clone(),
+            index: 0,
+            line,
+            origin: origin.clone(),
+            kind,
+        });
+    }
+
+    // Next line at the top level, from the innermost included file that
+    // still has lines
+    fn next_line(&mut self) -> Option<(usize, String, Rc<Origin>)> {
+        while let Some(file) = self.files.last_mut() {
+            if let Some((line, source)) = file.lines.next() {
+                return Some((line, source, file.origin.clone()));
+            }
+
+            let file = self.files.pop().unwrap();
+            self.parser.dialect = file.dialect;
+            self.parser.units = file.units;
+            self.parser.position.relative = file.relative;
+        }
+
+        let (line, source) = self.lines.next()?;
+        Some((line, source, self.root.clone()))
+    }
+
+    // Runs a line read at the top level, along with the block it opens
+    fn top_level(
+        &mut self,
+        line: usize,
+        source: String,
+        origin: &Rc<Origin>,
+    ) -> Result<(), ParseError> {
+        let source = match self.files.last_mut() {
+            Some(file) => file.numbers.strip(line, &source)?,
+            None => self.numbers.strip(line, &source)?,
+        };
+
+        if let Some(("INCLUDE", token)) = keyword(line, &source) {
+            return self.include(line, &source, &token, origin);
+        }
+
+        let statement = match self.files.last_mut() {
+            Some(file) => read_statement(&mut file.lines, &mut file.numbers, line, source)?,
+            None => read_statement(&mut self.lines, &mut self.numbers, line, source)?,
+        };
+
+        self.execute(&statement, origin)
+    }
+
+    // INCLUDE "path"
+    fn include(
+        &mut self,
+        line: usize,
+        source: &str,
+        keyword: &Token,
+        origin: &Rc<Origin>,
+    ) -> Result<(), ParseError> {
+        let path = include_path(source, keyword);
+
+        if path.text.is_empty() {
+            return Err(parser::arity_error(line, "INCLUDE", keyword, &[], 1));
+        }
+
+        let file = match origin.file.as_ref().and_then(|file| file.parent()) {
+            Some(directory) => directory.join(path.text),
+            None => PathBuf::from(path.text),
+        };
+        let failed = |e: std::io::Error| {
+            ParseError::new(line, &path, ParseErrorKind::IncludeFailed(e.to_string()))
+        };
+        let canonical = file.canonicalize().map_err(failed)?;
+
+        let open = self.files.iter().map(|file| &file.path);
+        if self
+            .root_path
+            .iter()
+            .chain(open)
+            .any(|open| *open == canonical)
+        {
+            return Err(ParseError::new(line, &path, ParseErrorKind::IncludeCycle));
+        }
+
+        let contents = fs::read_to_string(&file).map_err(failed)?;
+        let lines = contents
+            .lines()
+            .enumerate()
+            .map(|(line_idx, line)| (line_idx + 1, line.to_string()))
+            .collect::<Vec<(usize, String)>>();
+
+        let mut included_from = origin.included_from.clone();
+        included_from.push(Include {
+            file: origin.file.clone(),
+            line,
+        });
+
+        self.files.push(IncludedFile {
+            lines: lines.into_iter(),
+            numbers: LineNumbers::default(),
+            path: canonical,
+            origin: Rc::new(Origin {
+                file: Some(file.clone()),
+                included_from,
+            }),
+            dialect: self.parser.dialect,
+            units: self.parser.units,
+            relative: self.parser.position.relative,
+        });
+
+        if let Some(dialect) = Dialect::from_path(&file) {
+            self.parser.dialect = dialect;
+        }
+
+        Ok(())
+    }
+
+    fn execute(&mut self, statement: &Statement, origin: &Rc<Origin>) -> Result<(), ParseError> {
+        match statement {
+            Statement::Line(line, source) => {
+                let commands = self.parser.parse_line(*line, source)?;
+                self.pending.extend(
+                    commands
+                        .into_iter()
+                        .map(|command| (command, *line, origin.clone())),
+                );
+            }
+            Statement::Call(line, source) => self.call(*line, source)?,
+            Statement::Sub(subroutine) => {
+                if self.subroutines.contains_key(&subroutine.name) {
+                    return Err(ParseError {
+                        line: subroutine.line,
+                        columns: subroutine.columns.clone(),
+                        token: subroutine.name.clone(),
+                        kind: ParseErrorKind::DuplicateDefinition,
+                        origin: Box::default(),
+                    });
+                }
+
+                self.subroutines.insert(
+                    subroutine.name.clone(),
+                    (subroutine.clone(), origin.clone()),
+                );
+            }
+            Statement::Repeat { line, source, body } => {
+                let count = self.condition(*line, source)?.round();
+
+                if count < 0.0 {
+                    let terms = parser::tokenize(*line, source)?;
+                    return Err(ParseError::new(
+                        *line,
+                        &terms[0],
+                        ParseErrorKind::BadExpression(
+                            "repeat count must not be negative".to_string(),
+                        ),
+                    ));
+                }
+
+                if count >= 1.0 {
+                    let remaining = count as u64;
+                    self.push(*line, body, origin, FrameKind::Repeat { remaining });
+                }
+            }
+            Statement::While { line, source, body } => {
+                if self.condition(*line, source)? != 0.0 {
+                    let kind = FrameKind::While {
+                        line: *line,
+                        source: source.clone(),
+                    };
+                    self.push(*line, body, origin, kind);
+                }
+            }
+            Statement::If {
+                line,
+                source,
+                then,
+                otherwise,
+            } => {
+                let branch = if self.condition(*line, source)? != 0.0 {
+                    then
+                } else {
+                    otherwise
+                };
+                self.push(*line, branch, origin, FrameKind::Block);
+            }
+        }
+
+        Ok(())
+    }
+
+    // CALL name arg arg
+    fn call(&mut self, line: usize, source: &str) -> Result<(), ParseError> {
+        let terms = parser::tokenize(line, source)?;
+
+        let Some(name) = terms.get(1) else {
+            return Err(parser::arity_error(line, "CALL", &terms[0], &terms[1..], 1));
+        };
+
+        let Some((subroutine, origin)) = self.subroutines.get(&name.text.to_lowercase()).cloned()
+        else {
+            return Err(ParseError::new(
+                line,
+                name,
+                ParseErrorKind::UndefinedSubroutine,
+            ));
+        };
+
+        let arguments = &terms[2..];
+
+        if arguments.len() != subroutine.parameters.len() {
+            let call = format!("CALL {}", name.text);
+            return Err(parser::arity_error(
+                line,
+                &call,
+                &terms[0],
+                arguments,
+                subroutine.parameters.len(),
+            ));
+        }
+
+        let depth = self
+            .frames
+            .iter()
+            .filter(|frame| matches!(frame.kind, FrameKind::Call { .. }))
+            .count();
+
+        if depth == MAX_CALL_DEPTH {
+            return Err(ParseError::new(line, name, ParseErrorKind::RecursionLimit));
+        }
+
+        let values = arguments
+            .iter()
+            .map(|argument| self.parser.number(line, argument))
+            .collect::<Result<Vec<f32>, ParseError>>()?;
+
+        let saved = self.parser.variables.clone();
+        let dialect = self.parser.dialect;
+
+        for (parameter, value) in subroutine.parameters.iter().zip(values) {
+            self.parser.variables.insert(parameter.clone(), value);
+        }
+
+        self.push(
+            line,
+            &subroutine.body,
+            &origin,
+            FrameKind::Call { saved, dialect },
+        );
+
+        Ok(())
+    }
+
+    // Called once the innermost block has run its last statement
+    fn end_of_frame(&mut self) -> Result<(), ParseError> {
+        let frame = self.frames.last_mut().unwrap();
+
+        let repeat = match &mut frame.kind {
+            FrameKind::Repeat { remaining } => {
+                *remaining -= 1;
+                *remaining > 0
+            }
+            FrameKind::While { line, source } => {
+                let (line, source) = (*line, source.clone());
+                self.condition(line, &source)? != 0.0
+            }
+            FrameKind::Block | FrameKind::Call { .. } => false,
+        };
+
+        if repeat {
+            self.frames.last_mut().unwrap().index = 0;
+        } else {
+            self.pop();
+        }
+
+        Ok(())
+    }
+
+    // Leaving a subroutine drops its local variables
+    fn pop(&mut self) -> bool {
+        let Some(frame) = self.frames.pop() else {
+            return false;
+        };
+
+        if let FrameKind::Call { saved, dialect } = frame.kind {
+            self.parser.variables = saved;
+            self.parser.dialect = dialect;
+        }
+
+        true
+    }
+
+    // Why the program's lines ran out, when it was not the end of them
+    fn read_error(&mut self) -> Option<ParseError> {
+        let (line, error) = self.lines.error()?;
+
+        Some(ParseError {
+            line,
+            columns: 1..1,
+            token: String::new(),
+            kind: ParseErrorKind::ReadFailed(error.to_string()),
+            origin: Box::new(self.root.as_ref().clone()),
+        })
+    }
+
+    // Drops every open block after an error, leaving the variables as they
+    // were outside any subroutine
+    fn unwind(&mut self) {
+        while self.pop() {}
+    }
+}
+impl<I: Lines> Iterator for Interpreter<I> {
+    type Item = Result<Command, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut steps = 0;
+
+        loop {
+            if let Some((command, line, origin)) = self.pending.pop_front() {
+                self.location = Some((line, origin));
+                return Some(Ok(command));
+            }
+
+            if self.parser.ended {
+                return None;
+            }
+
+            // Only blocks can spin, top-level lines always move on through the
+            // program however many produce nothing
+            steps = match self.frames.is_empty() {
+                true => 0,
+                false => steps + 1,
+            };
+
+            if steps > MAX_IDLE_STEPS {
+                let frame = self.frames.first().unwrap();
+                let (line, origin) = (frame.line, frame.origin.as_ref().clone());
+                self.unwind();
+
+                return Some(Err(ParseError {
+                    line,
+                    columns: 1..1,
+                    token: String::new(),
+                    kind: ParseErrorKind::RunawayLoop,
+                    origin: Box::new(origin),
+                }));
+            }
+
+            let result = match self.frames.last_mut() {
+                Some(frame) if frame.index < frame.body.len() => {
+                    let body = frame.body.clone();
+                    let origin = frame.origin.clone();
+                    let index = frame.index;
+                    frame.index += 1;
+                    self.execute(&body[index], &origin)
+                        .map_err(|e| e.at(&origin))
+                }
+                Some(frame) => {
+                    let origin = frame.origin.clone();
+                    self.end_of_frame().map_err(|e| e.at(&origin))
+                }
+                None => {
+                    let Some((line, source, origin)) = self.next_line() else {
+                        return self.read_error().map(Err);
+                    };
+                    self.top_level(line, source, &origin)
+                        .map_err(|e| e.at(&origin))
+                }
+            };
+
+            if let Err(e) = result {
+                self.unwind();
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+// Runs a program without sending anything, collecting every error. Stops
+// after `limit` commands so a program that loops forever can still be checked.
+pub fn check(source: &str, dialect: Dialect, limit: usize) -> Result<usize, ParseReport> {
+    check_program(Interpreter::from_source(source, dialect), limit)
+}
+
+// Same as `check`, for a program that was already set up, e.g. with a path
+pub fn check_program<I: Lines>(
+    program: Interpreter<I>,
+    limit: usize,
+) -> Result<usize, ParseReport> {
+    let mut count = 0;
+    let mut errors: Vec<ParseError> = Vec::new();
+
+    for result in program {
+        match result {
+            Ok(_) => count += 1,
+            Err(e) => errors.push(e),
+        }
+
+        if count == limit {
+            break;
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(count)
+    } else {
+        Err(ParseReport { errors })
+    }
+}
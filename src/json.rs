@@ -0,0 +1,303 @@
+// JSON
+// ====
+// Just enough JSON for `check --json` and the language server: a value type,
+// a parser and compact printing. Object keys keep the order they were given.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+impl Json {
+    pub fn object(members: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    // Member of an object, None for other values
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(number) if *number >= 0.0 && number.fract() == 0.0 => {
+                Some(*number as usize)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Json> {
+        let mut reader = Reader {
+            text: text.as_bytes(),
+            offset: 0,
+        };
+        let value = reader.value()?;
+
+        reader.skip_whitespace();
+        (reader.offset == text.len()).then_some(value)
+    }
+}
+impl From<&str> for Json {
+    fn from(text: &str) -> Json {
+        Json::String(text.to_string())
+    }
+}
+impl From<String> for Json {
+    fn from(text: String) -> Json {
+        Json::String(text)
+    }
+}
+impl From<usize> for Json {
+    fn from(number: usize) -> Json {
+        Json::Number(number as f64)
+    }
+}
+impl From<bool> for Json {
+    fn from(value: bool) -> Json {
+        Json::Bool(value)
+    }
+}
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(number) if number.is_finite() => write!(f, "{}", number),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(text) => write!(f, "{}", quote(text)),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in members.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}:{}", quote(key), value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+// `text` as a JSON string literal
+pub fn quote(text: &str) -> String {
+    let mut json = String::from("\"");
+
+    for character in text.chars() {
+        match character {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            character if (character as u32) < 0x20 => {
+                json.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => json.push(character),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+struct Reader<'a> {
+    text: &'a [u8],
+    offset: usize,
+}
+impl Reader<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .text
+            .get(self.offset)
+            .is_some_and(|byte| byte.is_ascii_whitespace())
+        {
+            self.offset += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.offset).copied()
+    }
+
+    fn expect(&mut self, word: &str) -> Option<()> {
+        let end = self.offset + word.len();
+
+        if self.text.get(self.offset..end)? == word.as_bytes() {
+            self.offset = end;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        match self.peek()? {
+            b'n' => self.expect("null").map(|_| Json::Null),
+            b't' => self.expect("true").map(|_| Json::Bool(true)),
+            b'f' => self.expect("false").map(|_| Json::Bool(false)),
+            b'"' => self.string().map(Json::String),
+            b'[' => self.array(),
+            b'{' => self.object(),
+            _ => self.number(),
+        }
+    }
+
+    fn number(&mut self) -> Option<Json> {
+        let start = self.offset;
+
+        while self
+            .text
+            .get(self.offset)
+            .is_some_and(|byte| byte.is_ascii_digit() || b"+-.eE".contains(byte))
+        {
+            self.offset += 1;
+        }
+
+        let text = std::str::from_utf8(&self.text[start..self.offset]).ok()?;
+        text.parse::<f64>().ok().map(Json::Number)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect("\"")?;
+        let mut bytes = Vec::new();
+
+        loop {
+            let byte = *self.text.get(self.offset)?;
+            self.offset += 1;
+
+            match byte {
+                b'"' => return String::from_utf8(bytes).ok(),
+                b'\\' => {
+                    let escape = *self.text.get(self.offset)?;
+                    self.offset += 1;
+
+                    let character = match escape {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => self.unicode()?,
+                        byte => byte as char,
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(character.encode_utf8(&mut buffer).as_bytes());
+                }
+                byte => bytes.push(byte),
+            }
+        }
+    }
+
+    // The code unit(s) of a `\u` escape, a surrogate pair being two escapes
+    fn unicode(&mut self) -> Option<char> {
+        let unit = |reader: &mut Self| {
+            let hex = reader.text.get(reader.offset..reader.offset + 4)?;
+            reader.offset += 4;
+            u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+        };
+        let high = unit(self)?;
+
+        if (0xD800..0xDC00).contains(&high) {
+            self.expect("\\u")?;
+            let low = unit(self)?;
+            char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low.checked_sub(0xDC00)?))
+        } else {
+            char::from_u32(high)
+        }
+    }
+
+    fn array(&mut self) -> Option<Json> {
+        self.expect("[")?;
+        let mut values = Vec::new();
+
+        if self.peek()? == b']' {
+            self.offset += 1;
+            return Some(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+
+            match self.peek()? {
+                b',' => self.offset += 1,
+                b']' => {
+                    self.offset += 1;
+                    return Some(Json::Array(values));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn object(&mut self) -> Option<Json> {
+        self.expect("{")?;
+        let mut members = Vec::new();
+
+        if self.peek()? == b'}' {
+            self.offset += 1;
+            return Some(Json::Object(members));
+        }
+
+        loop {
+            self.peek()?;
+            let key = self.string()?;
+
+            if self.peek()? != b':' {
+                return None;
+            }
+            self.offset += 1;
+            members.push((key, self.value()?));
+
+            match self.peek()? {
+                b',' => self.offset += 1,
+                b'}' => {
+                    self.offset += 1;
+                    return Some(Json::Object(members));
+                }
+                _ => return None,
+            }
+        }
+    }
+}
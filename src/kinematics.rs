@@ -0,0 +1,744 @@
+// KINEMATICS
+// ==========
+// Where the tool is for a set of joint angles (forward kinematics), from the
+// Denavit–Hartenberg parameters of the arm.
+//
+// Joints follow `Axis`: X base, Y shoulder, Z elbow, A forearm roll, then the
+// differential wrist, B being its pitch and C the roll of the tool. `MN B`
+// moves both wrist joints, `MN C` the roll alone. Angles are in degrees and
+// lengths in millimetres, the tool pose is in the frame of the base.
+//
+// The host still sends `TG` and `TP` as Cartesian targets and the firmware
+// solves them itself. The reach check assumes it picks the same branch as
+// `Reach`, the valid configuration nearest the current joints; firmware that
+// picks another can move a joint the check never looked at.
+
+use crate::command::{Axis, Command};
+use crate::error::Origin;
+use crate::interpreter::{Interpreter, Lines};
+use crate::json;
+use crate::lint::{self, Limits, RobotProfile};
+use std::fmt;
+
+// One link in standard DH form: rotate `theta` (the joint angle plus
+// `offset`) about z, move `d` along z, `a` along x, then rotate `alpha`
+// about x
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DhLink {
+    pub a: f32,
+    pub alpha: f32,
+    pub d: f32,
+    pub offset: f32,
+}
+
+// Joint angles in X, Y, Z, A, B, C order
+pub type Joints = [f32; 6];
+
+pub const JOINT_NAMES: [&str; 6] = ["X", "Y", "Z", "A", "B", "C"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arm {
+    pub links: [DhLink; 6],
+}
+// Nominal Arctos v2.9 geometry. With every joint at 0 the upper arm stands
+// upright and the forearm and tool point forward along x.
+impl Default for Arm {
+    fn default() -> Self {
+        Arm {
+            links: [
+                DhLink {
+                    a: 20.174,
+                    alpha: -90.0,
+                    d: 287.87,
+                    offset: 0.0,
+                },
+                DhLink {
+                    a: 260.986,
+                    alpha: 0.0,
+                    d: 0.0,
+                    offset: -90.0,
+                },
+                DhLink {
+                    a: 19.219,
+                    alpha: -90.0,
+                    d: 0.0,
+                    offset: 0.0,
+                },
+                DhLink {
+                    a: 0.0,
+                    alpha: 90.0,
+                    d: 260.753,
+                    offset: 0.0,
+                },
+                DhLink {
+                    a: 0.0,
+                    alpha: -90.0,
+                    d: 0.0,
+                    offset: 0.0,
+                },
+                DhLink {
+                    a: 0.0,
+                    alpha: 0.0,
+                    d: 74.745,
+                    offset: 0.0,
+                },
+            ],
+        }
+    }
+}
+
+// Homogeneous transform, rows of a 4x4 matrix. Kept in f64 so chains of
+// links do not pile up rounding.
+pub type Transform = [[f64; 4]; 4];
+
+pub const IDENTITY: Transform = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+pub fn multiply(left: &Transform, right: &Transform) -> Transform {
+    let mut product = [[0.0; 4]; 4];
+
+    for (row, product_row) in product.iter_mut().enumerate() {
+        for (column, value) in product_row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| left[row][k] * right[k][column]).sum();
+        }
+    }
+
+    product
+}
+
+impl DhLink {
+    pub fn transform(&self, angle: f32) -> Transform {
+        let theta = ((angle + self.offset) as f64).to_radians();
+        let alpha = (self.alpha as f64).to_radians();
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let (sin_alpha, cos_alpha) = alpha.sin_cos();
+        let (a, d) = (self.a as f64, self.d as f64);
+
+        [
+            [
+                cos_theta,
+                -sin_theta * cos_alpha,
+                sin_theta * sin_alpha,
+                a * cos_theta,
+            ],
+            [
+                sin_theta,
+                cos_theta * cos_alpha,
+                -cos_theta * sin_alpha,
+                a * sin_theta,
+            ],
+            [0.0, sin_alpha, cos_alpha, d],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    }
+}
+
+// Tool position and orientation in the base frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub position: [f32; 3],
+    // Columns are the tool's x, y and z axes
+    pub rotation: [[f32; 3]; 3],
+}
+impl Pose {
+    pub fn from_transform(transform: &Transform) -> Pose {
+        let mut rotation = [[0.0; 3]; 3];
+
+        for (row, rotation_row) in rotation.iter_mut().enumerate() {
+            for (column, value) in rotation_row.iter_mut().enumerate() {
+                *value = transform[row][column] as f32;
+            }
+        }
+
+        Pose {
+            position: [
+                transform[0][3] as f32,
+                transform[1][3] as f32,
+                transform[2][3] as f32,
+            ],
+            rotation,
+        }
+    }
+
+    // Roll, pitch and yaw in degrees, the rotation being yaw about z, then
+    // pitch about y, then roll about x. Roll is 0 at pitch ±90.
+    pub fn orientation(&self) -> [f32; 3] {
+        let r = &self.rotation;
+        let pitch = (-r[2][0]).clamp(-1.0, 1.0).asin();
+
+        let (roll, yaw) = if r[2][0].abs() < 0.99999 {
+            (r[2][1].atan2(r[2][2]), r[1][0].atan2(r[0][0]))
+        } else {
+            (0.0, (-r[0][1]).atan2(r[1][1]))
+        };
+
+        [roll.to_degrees(), pitch.to_degrees(), yaw.to_degrees()]
+    }
+
+    // The tool's z axis, the direction it points in
+    pub fn approach(&self) -> [f32; 3] {
+        [
+            self.rotation[0][2],
+            self.rotation[1][2],
+            self.rotation[2][2],
+        ]
+    }
+}
+
+// Rotation of a roll, pitch and yaw in degrees, as `Pose::orientation` gives
+// them
+pub fn rotation(orientation: [f32; 3]) -> [[f32; 3]; 3] {
+    let [roll, pitch, yaw] = orientation.map(|angle| (angle as f64).to_radians());
+    let (sr, cr) = roll.sin_cos();
+    let (sp, cp) = pitch.sin_cos();
+    let (sy, cy) = yaw.sin_cos();
+
+    [
+        [cy * cp, cy * sp * sr - sy * cr, cy * sp * cr + sy * sr],
+        [sy * cp, sy * sp * sr + cy * cr, sy * sp * cr - cy * sr],
+        [-sp, cp * sr, cp * cr],
+    ]
+    .map(|row| row.map(|value| value as f32))
+}
+
+impl Arm {
+    // Frame of every link, the base first and the tool last
+    pub fn frames(&self, joints: &Joints) -> [Transform; 7] {
+        let mut frames = [IDENTITY; 7];
+
+        for (index, link) in self.links.iter().enumerate() {
+            frames[index + 1] = multiply(&frames[index], &link.transform(joints[index]));
+        }
+
+        frames
+    }
+
+    pub fn transform(&self, joints: &Joints) -> Transform {
+        self.frames(joints)[6]
+    }
+
+    // Tool pose for a set of joint angles
+    pub fn forward(&self, joints: &Joints) -> Pose {
+        Pose::from_transform(&self.transform(joints))
+    }
+}
+
+// Joint angles after a manual move, as the arm would hold them. Home is every
+// joint at 0.
+pub fn track(joints: &mut Joints, command: &Command) {
+    match command {
+        Command::MN(Axis::X(angle)) => joints[0] = *angle,
+        Command::MN(Axis::Y(angle)) => joints[1] = *angle,
+        Command::MN(Axis::Z(angle)) => joints[2] = *angle,
+        Command::MN(Axis::A(angle)) => joints[3] = *angle,
+        Command::MN(Axis::B(pitch, roll)) => {
+            joints[4] = *pitch;
+            joints[5] = *roll;
+        }
+        Command::MN(Axis::C(angle)) => joints[5] = *angle,
+        Command::RH => *joints = [0.0; 6],
+        _ => {}
+    }
+}
+
+// INVERSE KINEMATICS
+// ==================
+// Joint angles that put the tool at a target. The wrist axes meet in one
+// point, so the arm splits in two: X, Y and Z place the wrist centre, A, B
+// and C turn the tool. That gives up to 8 configurations (shoulder front or
+// back, elbow up or down, wrist flipped or not). A TP target gives the
+// orientation and the closed form finds every configuration. A TG target has
+// none, so the tool keeps the one it has, and when that cannot be held at the
+// target the position alone is solved numerically.
+
+// Largest distance, in mm, between a solution's tool and its target
+pub const TOLERANCE: f64 = 0.01;
+
+// Steps of the numeric solver before a target is given up on
+pub const MAX_ITERATIONS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unreachable {
+    OutOfReach,
+    JointLimits,
+}
+impl fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unreachable::OutOfReach => write!(f, "target is out of the arm's reach"),
+            Unreachable::JointLimits => {
+                write!(f, "target can only be reached beyond the joint limits")
+            }
+        }
+    }
+}
+impl std::error::Error for Unreachable {}
+
+// Limits of each joint in `Joints` order
+pub fn joint_limits(profile: &RobotProfile) -> [Limits; 6] {
+    [
+        profile.x,
+        profile.y,
+        profile.z,
+        profile.a,
+        profile.pitch,
+        profile.roll,
+    ]
+}
+
+// `angle` turned by whole turns to lie as close to `near` as it can
+fn unwrap(angle: f64, near: f32) -> f64 {
+    angle + ((near as f64 - angle) / 360.0).round() * 360.0
+}
+
+// Squared distance in joint space
+pub fn distance(from: &Joints, to: &Joints) -> f32 {
+    from.iter().zip(to).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+fn within(joints: &Joints, limits: &[Limits; 6]) -> bool {
+    joints
+        .iter()
+        .zip(limits)
+        .all(|(angle, limits)| limits.contains(*angle))
+}
+
+fn position_error(transform: &Transform, position: [f32; 3]) -> [f64; 3] {
+    [
+        position[0] as f64 - transform[0][3],
+        position[1] as f64 - transform[1][3],
+        position[2] as f64 - transform[2][3],
+    ]
+}
+
+fn norm(vector: &[f64]) -> f64 {
+    vector.iter().map(|value| value * value).sum::<f64>().sqrt()
+}
+
+// Solves `matrix * x = rhs` by Gaussian elimination, None when singular
+fn solve_linear(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<f64>) -> Option<Vec<f64>> {
+    let size = rhs.len();
+
+    for column in 0..size {
+        let pivot = (column..size).max_by(|a, b| {
+            matrix[*a][column]
+                .abs()
+                .total_cmp(&matrix[*b][column].abs())
+        })?;
+
+        if matrix[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+
+        for row in column + 1..size {
+            let factor = matrix[row][column] / matrix[column][column];
+            let pivot_row = matrix[column].clone();
+
+            for (value, pivot) in matrix[row].iter_mut().zip(&pivot_row).skip(column) {
+                *value -= factor * pivot;
+            }
+            rhs[row] -= factor * rhs[column];
+        }
+    }
+
+    let mut solution = vec![0.0; size];
+    for row in (0..size).rev() {
+        let sum: f64 = (row + 1..size).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (rhs[row] - sum) / matrix[row][row];
+    }
+
+    Some(solution)
+}
+
+impl Arm {
+    // Every configuration putting the tool at `position` with `rotation`,
+    // limits aside. Angles are unwrapped towards `near`.
+    pub fn configurations(
+        &self,
+        position: [f32; 3],
+        rotation: [[f32; 3]; 3],
+        near: &Joints,
+    ) -> Vec<Joints> {
+        let [l1, l2, l3, l4, _, l6] = self.links;
+        let (a1, d1, a2, a3, d4, d6) = (
+            l1.a as f64,
+            l1.d as f64,
+            l2.a as f64,
+            l3.a as f64,
+            l4.d as f64,
+            l6.d as f64,
+        );
+
+        // Wrist centre, back from the tool along its approach
+        let centre: [f64; 3] =
+            std::array::from_fn(|i| position[i] as f64 - d6 * rotation[i][2] as f64);
+        let horizontal = centre[0].hypot(centre[1]);
+        let height = centre[2] - d1;
+
+        let reach = a3.hypot(d4);
+        let bend = d4.atan2(a3);
+        let mut solutions = Vec::new();
+
+        // Straight above the base any heading will do, keep the current one
+        let heading = match horizontal > 1e-9 {
+            true => centre[1].atan2(centre[0]),
+            false => (near[0] as f64).to_radians(),
+        };
+
+        for (base, forward) in [
+            (heading, horizontal),
+            (heading + std::f64::consts::PI, -horizontal),
+        ] {
+            // Wrist centre in the plane of the upper arm and forearm, `v`
+            // pointing down
+            let (u, v) = (forward - a1, -height);
+            let cosine = (u * u + v * v - a2 * a2 - reach * reach) / (2.0 * a2 * reach);
+
+            if cosine.abs() > 1.0 {
+                continue;
+            }
+
+            for elbow in [cosine.acos(), -cosine.acos()] {
+                let shoulder = v.atan2(u) - (reach * elbow.sin()).atan2(a2 + reach * elbow.cos());
+                let arm: [f64; 3] = [
+                    base.to_degrees(),
+                    shoulder.to_degrees() - l2.offset as f64,
+                    (elbow - bend).to_degrees() - l3.offset as f64,
+                ];
+
+                solutions.extend(self.wrist(arm, rotation, near));
+            }
+        }
+
+        solutions
+            .into_iter()
+            .filter(|joints| {
+                let transform = self.transform(joints);
+                let rotation_error: f64 = (0..3)
+                    .flat_map(|row| (0..3).map(move |column| (row, column)))
+                    .map(|(row, column)| {
+                        (transform[row][column] - rotation[row][column] as f64).abs()
+                    })
+                    .fold(0.0, f64::max);
+
+                norm(&position_error(&transform, position)) < TOLERANCE && rotation_error < 1e-4
+            })
+            .collect()
+    }
+
+    // A, B and C turning the tool into `rotation` once X, Y and Z are set,
+    // both wrist flips
+    fn wrist(&self, arm: [f64; 3], rotation: [[f32; 3]; 3], near: &Joints) -> Vec<Joints> {
+        let base: Joints = [arm[0] as f32, arm[1] as f32, arm[2] as f32, 0.0, 0.0, 0.0];
+        let forearm = self.frames(&base)[3];
+
+        // Rotation left for the wrist, forearm frame transposed times target
+        let m: [[f64; 3]; 3] = std::array::from_fn(|row| {
+            std::array::from_fn(|column| {
+                (0..3)
+                    .map(|k| forearm[k][row] * rotation[k][column] as f64)
+                    .sum()
+            })
+        });
+
+        let pitch_sine = m[0][2].hypot(m[1][2]);
+        let mut wrists: Vec<[f64; 3]> = Vec::new();
+
+        if pitch_sine > 1e-6 {
+            for sine in [pitch_sine, -pitch_sine] {
+                wrists.push([
+                    (-m[1][2] / sine).atan2(-m[0][2] / sine),
+                    sine.atan2(m[2][2]),
+                    (-m[2][1] / sine).atan2(m[2][0] / sine),
+                ]);
+            }
+        } else {
+            // Forearm and tool rolls line up, keep A where it is
+            let roll = (near[3] as f64).to_radians();
+
+            wrists.push(match m[2][2] > 0.0 {
+                true => [roll, 0.0, m[1][0].atan2(m[0][0]) - roll],
+                false => [
+                    roll,
+                    std::f64::consts::PI,
+                    roll - (-m[1][0]).atan2(-m[0][0]),
+                ],
+            });
+        }
+
+        wrists
+            .into_iter()
+            .map(|wrist| {
+                let angles = [
+                    arm[0],
+                    arm[1],
+                    arm[2],
+                    wrist[0].to_degrees() - self.links[3].offset as f64,
+                    wrist[1].to_degrees() - self.links[4].offset as f64,
+                    wrist[2].to_degrees() - self.links[5].offset as f64,
+                ];
+                std::array::from_fn(|i| unwrap(angles[i], near[i]) as f32)
+            })
+            .collect()
+    }
+
+    // Joints near `seed` putting the tool at `position`, whatever its
+    // orientation, by damped least squares. None when it does not converge
+    // within the limits.
+    fn solve_position(
+        &self,
+        position: [f32; 3],
+        seed: &Joints,
+        limits: &[Limits; 6],
+    ) -> Option<Joints> {
+        const STEP: f64 = 1e-3;
+        const DAMPING: f64 = 0.5;
+        const MAX_STEP: f64 = 5.0;
+
+        let mut joints: [f64; 6] = seed.map(|angle| angle as f64);
+        let clamp = |joints: &mut [f64; 6]| {
+            for (angle, limits) in joints.iter_mut().zip(limits) {
+                *angle = angle.clamp(limits.min as f64, limits.max as f64);
+            }
+        };
+        let transform = |joints: &[f64; 6]| self.transform(&joints.map(|angle| angle as f32));
+
+        clamp(&mut joints);
+
+        for _ in 0..MAX_ITERATIONS {
+            let error = position_error(&transform(&joints), position);
+
+            if norm(&error) < TOLERANCE {
+                return Some(joints.map(|angle| angle as f32));
+            }
+
+            // Jacobian of the tool position, by finite differences
+            let columns: Vec<[f64; 3]> = (0..6)
+                .map(|joint| {
+                    let mut moved = joints;
+                    moved[joint] += STEP;
+                    let there = position_error(&transform(&moved), position);
+                    std::array::from_fn(|i| (error[i] - there[i]) / STEP)
+                })
+                .collect();
+
+            // (J Jᵀ + λ²I) y = e, then Δq = Jᵀ y
+            let product: Vec<Vec<f64>> = (0..3)
+                .map(|row| {
+                    (0..3)
+                        .map(|column| {
+                            let sum: f64 = columns.iter().map(|c| c[row] * c[column]).sum();
+                            sum + if row == column {
+                                DAMPING * DAMPING
+                            } else {
+                                0.0
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+            let y = solve_linear(product, error.to_vec())?;
+            let delta: Vec<f64> = columns
+                .iter()
+                .map(|column| (0..3).map(|i| column[i] * y[i]).sum())
+                .collect();
+
+            // Small steps, far from the target the linear model is poor
+            let scale = (MAX_STEP
+                / delta
+                    .iter()
+                    .fold(0.0, |max, step| f64::max(max, step.abs())))
+            .min(1.0);
+
+            for (angle, step) in joints.iter_mut().zip(&delta) {
+                *angle += step * scale;
+            }
+            clamp(&mut joints);
+        }
+
+        None
+    }
+
+    // Every valid configuration for a TG target, nearest to `current` first.
+    // The tool keeps its orientation where the target allows it.
+    pub fn inverse(
+        &self,
+        position: [f32; 3],
+        current: &Joints,
+        limits: &[Limits; 6],
+    ) -> Result<Vec<Joints>, Unreachable> {
+        let rotation = self.forward(current).rotation;
+        let configurations = self.configurations(position, rotation, current);
+        let mut valid: Vec<Joints> = configurations
+            .iter()
+            .filter(|joints| within(joints, limits))
+            .copied()
+            .collect();
+
+        if valid.is_empty() {
+            let home = [0.0; 6];
+            valid.extend(
+                [current, &home]
+                    .into_iter()
+                    .find_map(|seed| self.solve_position(position, seed, limits)),
+            );
+        }
+
+        if valid.is_empty() {
+            let reachable = !configurations.is_empty()
+                || self
+                    .solve_position(position, current, &[Limits::new(-720.0, 720.0); 6])
+                    .is_some();
+
+            return Err(match reachable {
+                true => Unreachable::JointLimits,
+                false => Unreachable::OutOfReach,
+            });
+        }
+
+        Ok(nearest_first(valid, current))
+    }
+
+    // Every valid configuration for a TP target, nearest to `current` first
+    pub fn inverse_pose(
+        &self,
+        position: [f32; 3],
+        orientation: [f32; 3],
+        current: &Joints,
+        limits: &[Limits; 6],
+    ) -> Result<Vec<Joints>, Unreachable> {
+        let configurations = self.configurations(position, rotation(orientation), current);
+
+        if configurations.is_empty() {
+            return Err(Unreachable::OutOfReach);
+        }
+
+        let valid: Vec<Joints> = configurations
+            .into_iter()
+            .filter(|joints| within(joints, limits))
+            .collect();
+
+        match valid.is_empty() {
+            true => Err(Unreachable::JointLimits),
+            false => Ok(nearest_first(valid, current)),
+        }
+    }
+}
+
+fn nearest_first(mut solutions: Vec<Joints>, current: &Joints) -> Vec<Joints> {
+    solutions.sort_by(|a, b| distance(a, current).total_cmp(&distance(b, current)));
+    solutions
+}
+
+// A TG or TP the arm cannot reach, found before anything is sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReachError {
+    pub line: usize,
+    pub origin: Origin,
+    pub command: Command,
+    pub reason: Unreachable,
+}
+impl ReachError {
+    // One line JSON object, like `lint::error_json`
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"severity\":\"error\",\"file\":{},\"line\":{},\"code\":\"unreachable\",\"message\":{},\"command\":{}}}",
+            lint::json_file(&self.origin),
+            self.line,
+            json::quote(&self.reason.to_string()),
+            json::quote(&self.command.to_string()),
+        )
+    }
+}
+impl fmt::Display for ReachError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.origin.file {
+            write!(f, "{} ", file.display())?;
+        }
+
+        write!(f, "line {}: {} ({})", self.line, self.reason, self.command)
+    }
+}
+
+// Follows the joints command by command, solving every target from where the
+// arm is. A target that cannot be reached leaves the joints where they were.
+pub struct Reach<'a> {
+    arm: &'a Arm,
+    limits: [Limits; 6],
+    pub joints: Joints,
+}
+impl<'a> Reach<'a> {
+    pub fn new(arm: &'a Arm, profile: &RobotProfile) -> Reach<'a> {
+        Reach {
+            arm,
+            limits: joint_limits(profile),
+            joints: [0.0; 6],
+        }
+    }
+
+    pub fn command(&mut self, command: &Command) -> Result<(), Unreachable> {
+        let solutions = match *command {
+            Command::TG(x, y, z) => self.arm.inverse([x, y, z], &self.joints, &self.limits),
+            Command::TP(x, y, z, roll, pitch, yaw) => {
+                self.arm
+                    .inverse_pose([x, y, z], [roll, pitch, yaw], &self.joints, &self.limits)
+            }
+            Command::HM(..) => {
+                self.joints = [0.0; 6];
+                return Ok(());
+            }
+            _ => {
+                track(&mut self.joints, command);
+                return Ok(());
+            }
+        };
+
+        self.joints = solutions?[0];
+        Ok(())
+    }
+}
+
+// Runs `Reach` through a program and reports the targets it cannot reach.
+// Parse errors are left to `interpreter::check_program`. Stops after `limit`
+// commands.
+pub fn check_reach<I: Lines>(
+    mut program: Interpreter<I>,
+    arm: &Arm,
+    profile: &RobotProfile,
+    limit: usize,
+) -> Result<usize, Vec<ReachError>> {
+    let mut reach = Reach::new(arm, profile);
+    let mut errors = Vec::new();
+    let mut count = 0;
+
+    while count < limit
+        && let Some(result) = program.next()
+    {
+        let Ok(command) = result else {
+            continue;
+        };
+        count += 1;
+
+        if let Err(reason) = reach.command(&command) {
+            let (line, origin) = program.location().unwrap();
+            errors.push(ReachError {
+                line,
+                origin: origin.clone(),
+                command,
+                reason,
+            });
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(count),
+        false => Err(errors),
+    }
+}
@@ -0,0 +1,29 @@
+// GCODE PARSER
+// ===========
+// Parses robot arm G-code files (.rgcf) into commands and streams them to the
+// Arduino Mega driving the Arctos Robot Arm v2.9.
+
+pub mod ast;
+pub mod binary;
+pub mod command;
+pub mod error;
+pub mod executor;
+pub mod expr;
+pub mod format;
+pub mod gcode;
+pub mod interpreter;
+pub mod json;
+pub mod kinematics;
+pub mod lint;
+pub mod lsp;
+pub mod motion;
+pub mod parser;
+pub mod poses;
+pub mod position;
+pub mod serial;
+pub mod units;
+
+pub use command::{Axis, Command};
+pub use error::{ParseError, ParseErrorKind, ParseReport};
+pub use interpreter::{Interpreter, Lines};
+pub use parser::{Dialect, Parser, parse_program, parse_program_as};
@@ -0,0 +1,336 @@
+// LINTER
+// ======
+// Runs a program without sending it and warns about commands that parse but
+// are probably mistakes:
+//
+// motion-before-homing     TG, TP, MN or RH before the arm has been homed (HM)
+// command-after-stop       Anything but RS after a force stop (FS)
+// reset-without-homing     RS followed by a move before HM
+// no-op                    NO lines
+// duplicate-target         TG or TP to where the previous move already went
+// claw-out-of-range        CL value outside the gripper's range
+// joint-limit              MN beyond the joint limits of the arm
+//
+// Diagnostics print as text, or as one JSON object per line for tools.
+
+use crate::command::{Axis, Command};
+use crate::error::{Origin, ParseError, ParseReport};
+use crate::interpreter::{Interpreter, Lines};
+use crate::json;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub min: f32,
+    pub max: f32,
+}
+impl Limits {
+    pub const fn new(min: f32, max: f32) -> Limits {
+        Limits { min, max }
+    }
+
+    pub fn contains(&self, value: f32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+// What the arm can do. Joint limits are in degrees, the claw's ranges in the
+// units of the CL parameters. `roll` limits the tool roll joint, which both
+// `MN B` and `MN C` move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RobotProfile {
+    pub x: Limits,
+    pub y: Limits,
+    pub z: Limits,
+    pub a: Limits,
+    pub pitch: Limits,
+    pub roll: Limits,
+    pub claw_open: Limits,
+    pub claw_force: Limits,
+    pub claw_speed: Limits,
+    pub claw_accel: Limits,
+    pub claw_hold: Limits,
+}
+// Arctos v2.9 as built, with the stock gripper
+impl Default for RobotProfile {
+    fn default() -> Self {
+        RobotProfile {
+            x: Limits::new(-170.0, 170.0),
+            y: Limits::new(-90.0, 90.0),
+            z: Limits::new(-120.0, 120.0),
+            a: Limits::new(-180.0, 180.0),
+            pitch: Limits::new(-100.0, 100.0),
+            roll: Limits::new(-180.0, 180.0),
+            claw_open: Limits::new(0.0, 60.0),
+            claw_force: Limits::new(0.0, 10.0),
+            claw_speed: Limits::new(0.0, 50.0),
+            claw_accel: Limits::new(0.0, 20.0),
+            claw_hold: Limits::new(0.0, 60_000.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintKind {
+    MotionBeforeHoming,
+    CommandAfterStop,
+    ResetWithoutHoming,
+    NoOp,
+    DuplicateTarget,
+    ClawOutOfRange(&'static str, Limits),
+    JointLimit(&'static str, Limits),
+}
+impl LintKind {
+    // Stable name for tools
+    pub fn code(&self) -> &'static str {
+        match self {
+            LintKind::MotionBeforeHoming => "motion-before-homing",
+            LintKind::CommandAfterStop => "command-after-stop",
+            LintKind::ResetWithoutHoming => "reset-without-homing",
+            LintKind::NoOp => "no-op",
+            LintKind::DuplicateTarget => "duplicate-target",
+            LintKind::ClawOutOfRange(..) => "claw-out-of-range",
+            LintKind::JointLimit(..) => "joint-limit",
+        }
+    }
+}
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LintKind::MotionBeforeHoming => write!(f, "arm moves before it has been homed"),
+            LintKind::CommandAfterStop => write!(f, "command follows a force stop"),
+            LintKind::ResetWithoutHoming => {
+                write!(f, "reset is followed by a move without homing first")
+            }
+            LintKind::NoOp => write!(f, "NO does nothing"),
+            LintKind::DuplicateTarget => write!(f, "target is where the arm already is"),
+            LintKind::ClawOutOfRange(parameter, limits) => write!(
+                f,
+                "claw {} is outside {}..{}",
+                parameter, limits.min, limits.max
+            ),
+            LintKind::JointLimit(joint, limits) => write!(
+                f,
+                "{} is outside its limits of {}..{} degrees",
+                joint, limits.min, limits.max
+            ),
+        }
+    }
+}
+
+// A warning about the command lowered from `line`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub origin: Origin,
+    pub command: Command,
+    pub kind: LintKind,
+}
+impl Diagnostic {
+    // One line JSON object
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"severity\":\"warning\",\"file\":{},\"line\":{},\"code\":\"{}\",\"message\":{},\"command\":{}}}",
+            json_file(&self.origin),
+            self.line,
+            self.kind.code(),
+            json::quote(&self.kind.to_string()),
+            json::quote(&self.command.to_string()),
+        )
+    }
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.origin.file {
+            write!(f, "{} ", file.display())?;
+        }
+
+        write!(
+            f,
+            "line {}: warning[{}]: {} ({})",
+            self.line,
+            self.kind.code(),
+            self.kind,
+            self.command
+        )
+    }
+}
+
+// JSON form of a parse error, in the same shape as a diagnostic
+pub fn error_json(error: &ParseError) -> String {
+    format!(
+        "{{\"severity\":\"error\",\"file\":{},\"line\":{},\"columns\":[{},{}],\"message\":{}}}",
+        json_file(&error.origin),
+        error.line,
+        error.columns.start,
+        error.columns.end,
+        json::quote(&error.to_string()),
+    )
+}
+
+pub(crate) fn json_file(origin: &Origin) -> String {
+    match &origin.file {
+        Some(file) => json::quote(&file.display().to_string()),
+        None => "null".to_string(),
+    }
+}
+
+// Follows the state of the arm through a program, command by command
+pub struct Linter<'a> {
+    profile: &'a RobotProfile,
+    homed: bool,
+    stopped: bool,
+    reset: Option<(usize, Origin, Command)>,
+    last_target: Option<Command>,
+    diagnostics: Vec<Diagnostic>,
+}
+impl<'a> Linter<'a> {
+    pub fn new(profile: &'a RobotProfile) -> Linter<'a> {
+        Linter {
+            profile,
+            homed: false,
+            stopped: false,
+            reset: None,
+            last_target: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, line: usize, origin: &Origin, command: &Command, kind: LintKind) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            origin: origin.clone(),
+            command: *command,
+            kind,
+        });
+    }
+
+    fn limit(
+        &mut self,
+        line: usize,
+        origin: &Origin,
+        command: &Command,
+        checks: &[(&'static str, Limits, f32)],
+        kind: fn(&'static str, Limits) -> LintKind,
+    ) {
+        for (name, limits, value) in checks {
+            if !limits.contains(*value) {
+                self.warn(line, origin, command, kind(name, *limits));
+            }
+        }
+    }
+
+    pub fn command(&mut self, line: usize, origin: &Origin, command: &Command) {
+        if self.stopped && *command != Command::RS {
+            self.warn(line, origin, command, LintKind::CommandAfterStop);
+            // Once per stop, the rest would say the same
+            self.stopped = false;
+        }
+
+        let moves = matches!(
+            command,
+            Command::TG(..) | Command::TP(..) | Command::MN(_) | Command::RH
+        );
+
+        if moves && !self.homed {
+            match self.reset.take() {
+                Some((line, origin, reset)) => {
+                    self.warn(line, &origin, &reset, LintKind::ResetWithoutHoming)
+                }
+                None => self.warn(line, origin, command, LintKind::MotionBeforeHoming),
+            }
+            // Warned once, until the next reset
+            self.homed = true;
+        }
+
+        let profile = self.profile;
+
+        match command {
+            Command::NO => self.warn(line, origin, command, LintKind::NoOp),
+            Command::HM(..) => {
+                self.homed = true;
+                self.reset = None;
+            }
+            Command::FS => self.stopped = true,
+            Command::RS => {
+                self.stopped = false;
+                self.homed = false;
+                self.reset = Some((line, origin.clone(), *command));
+            }
+            Command::TG(..) | Command::TP(..) if self.last_target == Some(*command) => {
+                self.warn(line, origin, command, LintKind::DuplicateTarget)
+            }
+            Command::CL(open, force, speed, accel, hold) => {
+                let checks = [
+                    ("OPEN", profile.claw_open, *open),
+                    ("FORCE", profile.claw_force, *force),
+                    ("SPEED", profile.claw_speed, *speed),
+                    ("ACCEL", profile.claw_accel, *accel),
+                    ("HOLD", profile.claw_hold, *hold),
+                ];
+                self.limit(line, origin, command, &checks, LintKind::ClawOutOfRange);
+            }
+            Command::MN(axis) => {
+                let checks = match *axis {
+                    Axis::X(angle) => vec![("joint X", profile.x, angle)],
+                    Axis::Y(angle) => vec![("joint Y", profile.y, angle)],
+                    Axis::Z(angle) => vec![("joint Z", profile.z, angle)],
+                    Axis::A(angle) => vec![("joint A", profile.a, angle)],
+                    Axis::B(pitch, roll) => vec![
+                        ("wrist pitch", profile.pitch, pitch),
+                        ("wrist roll", profile.roll, roll),
+                    ],
+                    Axis::C(angle) => vec![("wrist roll", profile.roll, angle)],
+                };
+                self.limit(line, origin, command, &checks, LintKind::JointLimit);
+            }
+            _ => {}
+        }
+
+        if moves || matches!(command, Command::HM(..)) {
+            self.last_target = Some(*command);
+        }
+    }
+
+    // Diagnostics found since the last call, for warning as a program runs
+    pub fn take(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    pub fn finish(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+// Lints a program, or reports its parse errors. Stops after `limit` commands
+// like `interpreter::check`.
+pub fn lint<I: Lines>(
+    mut program: Interpreter<I>,
+    profile: &RobotProfile,
+    limit: usize,
+) -> Result<Vec<Diagnostic>, ParseReport> {
+    let mut linter = Linter::new(profile);
+    let mut errors: Vec<ParseError> = Vec::new();
+    let mut count = 0;
+
+    while let Some(result) = program.next() {
+        match result {
+            Ok(command) => {
+                let (line, origin) = program.location().unwrap();
+                linter.command(line, origin, &command);
+                count += 1;
+            }
+            Err(e) => errors.push(e),
+        }
+
+        if count == limit {
+            break;
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(linter.finish())
+    } else {
+        Err(ParseReport { errors })
+    }
+}
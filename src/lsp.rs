@@ -0,0 +1,771 @@
+// LANGUAGE SERVER
+// ===============
+// Speaks the Language Server Protocol over stdin/stdout so editors can check
+// programs as they are written (`roboarm_gcode lsp`):
+//
+// diagnostics        Parse errors and lint warnings, on open and on every edit
+// hover              What a command does and the arguments it takes
+// completion         Commands, MN axes, UN units, named arguments, CALL
+//                    subroutines and @poses
+// definition         The SUB of a CALL, the file of an INCLUDE, the POSE of
+//                    an @pose
+//
+// Documents are synced in full. Columns are counted in characters, which is
+// what editors send for the ASCII programs are written in.
+
+use crate::ast::{Line, Term};
+use crate::error::{Origin, ParseError};
+use crate::interpreter::Interpreter;
+use crate::json::Json;
+use crate::lint::{Diagnostic, Linter, RobotProfile};
+use crate::parser::{self, Dialect};
+use crate::poses::{self, POSE_FILE};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+// Commands run per check, so a program that loops forever still gets checked
+pub const CHECK_LIMIT: usize = 100_000;
+
+// Hover text per command and keyword. Usage is taken from
+// `parser::SIGNATURES` where the command has one.
+pub const DOCS: &[(&str, &str)] = &[
+    ("NO", "Does nothing."),
+    (
+        "HM",
+        "Homes the arm, then takes X Y Z (mm) as the position of the tool.",
+    ),
+    (
+        "TG",
+        "Moves the tool to X Y Z (mm), or by X Y Z after `RP`. `TG @name` moves to a named pose. Takes `F=` and `ACC=` overrides.",
+    ),
+    (
+        "TP",
+        "Moves the tool to X Y Z (mm) with the orientation ROLL PITCH YAW (degrees, yaw about z, then pitch about y, then roll about x). Left out, the orientation is 180 0 0, the tool pointing straight down; positionally it takes 3 to 6 values, so `TP 120 40 30` keeps the default orientation. Position follows `RP` like TG, the orientation is always absolute. Takes `F=` and `ACC=` overrides.",
+    ),
+    (
+        "TR",
+        "Moves the tool by X Y Z (mm) from where it is, whatever the positioning mode. Axes left out stay put.",
+    ),
+    (
+        "CL",
+        "Drives the claw.\n\n- OPEN: opening of the jaws\n- FORCE: gripping force\n- SPEED: speed of the jaws\n- ACCEL: acceleration of the jaws\n- HOLD: time to hold the grip (ms)",
+    ),
+    ("DW", "Pauses the arm for MS milliseconds."),
+    (
+        "WI",
+        "Holds back the rest of the program until every queued move has finished.",
+    ),
+    (
+        "MN",
+        "Moves a single joint to an angle (degrees). `MN B` takes the wrist pitch and roll.",
+    ),
+    ("RH", "Returns the arm to its home position."),
+    (
+        "RS",
+        "Resets the controller. Home the arm again before moving it.",
+    ),
+    (
+        "FS",
+        "Stops the arm at once. Only `RS` is accepted after it.",
+    ),
+    (
+        "FR",
+        "Sets the feed rate (mm/min) of the moves that follow.",
+    ),
+    (
+        "AC",
+        "Sets the acceleration (mm/s²) of the moves that follow.",
+    ),
+    (
+        "UN",
+        "`UN MM|IN` sets the unit of targets, `UN DEG|RAD|STEP` the unit of MN angles (TP angles in DEG or RAD).",
+    ),
+    ("AP", "Absolute positioning, TG takes a target (default)."),
+    ("RP", "Relative positioning, TG takes an offset."),
+    ("POSE", "`POSE name X Y Z` names a target for `TG @name`."),
+    (
+        "SUB",
+        "`SUB name(a, b)` defines a subroutine, up to its `END`.",
+    ),
+    (
+        "CALL",
+        "`CALL name 1 2` runs a subroutine with its parameters.",
+    ),
+    ("REPEAT", "`REPEAT n` runs the block up to `END` n times."),
+    (
+        "WHILE",
+        "`WHILE cond` runs the block up to `END` while cond is not 0.",
+    ),
+    (
+        "IF",
+        "`IF cond` runs the block up to `ELSE` or `END` when cond is not 0.",
+    ),
+    ("ELSE", "Runs up to `END` when the `IF` condition was 0."),
+    ("END", "Closes a SUB, REPEAT, WHILE or IF block."),
+    (
+        "INCLUDE",
+        "`INCLUDE \"path.rgcf\"` runs another file in place, relative to this one.",
+    ),
+];
+
+const AXES: &[&str] = &["X", "Y", "Z", "A", "B", "C"];
+const UNITS: &[&str] = &["MM", "IN", "DEG", "RAD", "STEP"];
+
+// LSP severities and completion item kinds
+const SEVERITY_ERROR: usize = 1;
+const SEVERITY_WARNING: usize = 2;
+const KIND_FUNCTION: usize = 3;
+const KIND_FIELD: usize = 5;
+const KIND_KEYWORD: usize = 14;
+const KIND_ENUM_MEMBER: usize = 20;
+const KIND_CONSTANT: usize = 21;
+
+// Reads one message, None once the client has closed the stream
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Json>> {
+    let mut length: Option<usize> = None;
+
+    loop {
+        let mut header = String::new();
+
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            length = value.trim().parse().ok();
+        }
+    }
+
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let length = length.ok_or_else(|| invalid("message has no Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let body = String::from_utf8(body).map_err(|_| invalid("message is not UTF-8"))?;
+    Json::parse(&body)
+        .map(Some)
+        .ok_or_else(|| invalid("message is not valid JSON"))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+// Path of a `file://` URI
+pub fn uri_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::new();
+    let mut rest = path.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+pub fn path_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    uri
+}
+
+// Path following `INCLUDE` on a line, if it is an INCLUDE line
+fn include_target(source: &str) -> Option<&str> {
+    let code = source.split(';').next().unwrap_or("").trim();
+    let rest = code.strip_prefix("INCLUDE")?;
+
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    Some(rest.trim().trim_matches('"')).filter(|path| !path.is_empty())
+}
+
+// Lowercase name a `SUB` or `POSE` line defines
+fn defined_name(source: &str, keyword: &str) -> Option<String> {
+    let line = Line::parse(0, source, Dialect::Rgcf);
+    let terms: Vec<&Term> = line.terms().collect();
+
+    match terms.as_slice() {
+        [first, name, ..] if first.text == keyword => {
+            let name = name.text.split('(').next().unwrap_or("");
+            Some(name.to_lowercase()).filter(|name| !name.is_empty())
+        }
+        _ => None,
+    }
+}
+
+// Usage line of a command, from its signature
+fn usage(name: &str) -> Option<String> {
+    let forms: Vec<String> = parser::SIGNATURES
+        .iter()
+        .filter(|(entry, _)| entry.split(' ').next() == Some(name))
+        .map(|(entry, parameters)| {
+            let mut form = entry.to_string();
+
+            for parameter in parameters.iter() {
+                match parameter.default {
+                    Some(default) => form.push_str(&format!(" [{}={}]", parameter.name, default)),
+                    None => form.push_str(&format!(" {}", parameter.name)),
+                }
+            }
+
+            form
+        })
+        .collect();
+
+    (!forms.is_empty()).then(|| forms.join("\n"))
+}
+
+fn hover_text(name: &str) -> Option<String> {
+    let (_, doc) = DOCS.iter().find(|(entry, _)| *entry == name)?;
+
+    Some(match usage(name) {
+        Some(usage) => format!("```rgcf\n{}\n```\n\n{}", usage, doc),
+        None => doc.to_string(),
+    })
+}
+
+// A file of the program, the document itself or one it pulls in
+struct Source {
+    path: Option<PathBuf>,
+    text: String,
+}
+
+// The document, the files it includes at any depth and the pose file next to
+// it, read from disk
+fn sources(path: Option<&Path>, text: &str) -> Vec<Source> {
+    let mut sources = vec![Source {
+        path: path.map(Path::to_path_buf),
+        text: text.to_string(),
+    }];
+    let mut seen: Vec<PathBuf> = path
+        .and_then(|path| path.canonicalize().ok())
+        .into_iter()
+        .collect();
+    let mut index = 0;
+
+    while index < sources.len() {
+        let source = &sources[index];
+        let directory = source.path.as_ref().and_then(|path| path.parent());
+        let mut files: Vec<PathBuf> = source
+            .text
+            .lines()
+            .filter_map(include_target)
+            .map(|target| match directory {
+                Some(directory) => directory.join(target),
+                None => PathBuf::from(target),
+            })
+            .collect();
+
+        if index == 0 {
+            files.extend(path.map(|path| path.with_file_name(POSE_FILE)));
+        }
+        index += 1;
+
+        for file in files {
+            let Ok(canonical) = file.canonicalize() else {
+                continue;
+            };
+
+            if seen.contains(&canonical) {
+                continue;
+            }
+            seen.push(canonical);
+
+            if let Ok(text) = fs::read_to_string(&file) {
+                sources.push(Source {
+                    path: Some(file),
+                    text,
+                });
+            }
+        }
+    }
+
+    sources
+}
+
+fn position(line: usize, character: usize) -> Json {
+    Json::object(vec![("line", line.into()), ("character", character.into())])
+}
+
+// Zero-based range of the 1-based `line` and `columns`
+fn range(line: usize, columns: Range<usize>) -> Json {
+    Json::object(vec![
+        ("start", position(line - 1, columns.start - 1)),
+        ("end", position(line - 1, columns.end - 1)),
+    ])
+}
+
+fn location(uri: String, line: usize) -> Json {
+    Json::object(vec![("uri", uri.into()), ("range", range(line, 1..1))])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn response(id: &Json, result: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id.clone()),
+        ("result", result),
+    ])
+}
+
+fn completion(label: &str, kind: usize, detail: Option<String>) -> Json {
+    let mut item = vec![("label", label.into()), ("kind", kind.into())];
+    item.extend(detail.map(|detail| ("detail", detail.into())));
+    Json::object(item)
+}
+
+pub struct Server {
+    documents: HashMap<String, String>,
+    profile: RobotProfile,
+    shut_down: bool,
+}
+impl Default for Server {
+    fn default() -> Self {
+        Server::new(RobotProfile::default())
+    }
+}
+impl Server {
+    pub fn new(profile: RobotProfile) -> Server {
+        Server {
+            documents: HashMap::new(),
+            profile,
+            shut_down: false,
+        }
+    }
+
+    // Whether the client asked the server to shut down before exiting
+    pub fn shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    // Responses and notifications for a message from the client
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .to_string();
+
+        let Some(id) = message.get("id") else {
+            return self.notify(method, params, uri);
+        };
+
+        let result = match method {
+            "initialize" => Json::object(vec![
+                (
+                    "capabilities",
+                    Json::object(vec![
+                        ("textDocumentSync", 1.into()),
+                        ("hoverProvider", true.into()),
+                        (
+                            "completionProvider",
+                            Json::object(vec![(
+                                "triggerCharacters",
+                                Json::Array(vec!["@".into()]),
+                            )]),
+                        ),
+                        ("definitionProvider", true.into()),
+                    ]),
+                ),
+                (
+                    "serverInfo",
+                    Json::object(vec![("name", "roboarm_gcode".into())]),
+                ),
+            ]),
+            "shutdown" => {
+                self.shut_down = true;
+                Json::Null
+            }
+            "textDocument/hover" => self.hover(&uri, params),
+            "textDocument/completion" => self.complete(&uri, params),
+            "textDocument/definition" => self.definition(&uri, params),
+            _ => {
+                return vec![Json::object(vec![
+                    ("jsonrpc", "2.0".into()),
+                    ("id", id.clone()),
+                    (
+                        "error",
+                        Json::object(vec![
+                            ("code", Json::Number(-32601.0)),
+                            ("message", format!("unsupported method '{}'", method).into()),
+                        ]),
+                    ),
+                ])];
+            }
+        };
+
+        vec![response(id, result)]
+    }
+
+    fn notify(&mut self, method: &str, params: &Json, uri: String) -> Vec<Json> {
+        let text = match method {
+            "textDocument/didOpen" => params
+                .get("textDocument")
+                .and_then(|document| document.get("text")),
+            // Full sync, the last change holds the whole document
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .and_then(Json::as_array)
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text")),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![self.publish(uri, Vec::new())];
+            }
+            _ => return Vec::new(),
+        };
+
+        let Some(text) = text.and_then(Json::as_str) else {
+            return Vec::new();
+        };
+
+        let diagnostics = self.diagnostics(&uri, text);
+        self.documents.insert(uri.clone(), text.to_string());
+
+        vec![self.publish(uri, diagnostics)]
+    }
+
+    fn publish(&self, uri: String, diagnostics: Vec<Json>) -> Json {
+        notification(
+            "textDocument/publishDiagnostics",
+            Json::object(vec![
+                ("uri", uri.into()),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        )
+    }
+
+    // Where a problem found on `line` of `origin` shows in the document: on
+    // the line itself, on the INCLUDE line that led to it, or on the first
+    // line when it comes from elsewhere, e.g. the pose file
+    fn place(
+        path: Option<&Path>,
+        origin: &Origin,
+        line: usize,
+        columns: Range<usize>,
+    ) -> (usize, Range<usize>, bool) {
+        let document = origin.file.as_deref() == path;
+
+        match origin.included_from.first() {
+            None if document => (line, columns, true),
+            Some(include) if include.file.as_deref() == path => (include.line, 1..1, false),
+            _ => (1, 1..1, false),
+        }
+    }
+
+    // Diagnostics of a document, checked as the binary would run it
+    pub fn diagnostics(&self, uri: &str, text: &str) -> Vec<Json> {
+        let path = uri_path(uri);
+        let dialect = path
+            .as_deref()
+            .and_then(Dialect::from_path)
+            .unwrap_or(Dialect::Rgcf);
+        let lines: Vec<&str> = text.lines().collect();
+        let line_end = |line: usize| lines.get(line - 1).map_or(0, |text| text.chars().count()) + 1;
+
+        let mut errors: Vec<ParseError> = Vec::new();
+        let mut warnings: Vec<Diagnostic> = Vec::new();
+        let mut pose_library = poses::Poses::new();
+
+        match path.as_deref().and_then(poses::load_beside) {
+            Some((_, Ok(loaded))) => pose_library = loaded,
+            Some((_, Err(report))) => errors.extend(report.errors),
+            None => {}
+        }
+
+        let mut program = Interpreter::from_source(text, dialect).with_poses(pose_library);
+        if let Some(path) = path.as_deref() {
+            program = program.with_path(path);
+        }
+
+        let mut linter = Linter::new(&self.profile);
+        let mut count = 0;
+
+        while count < CHECK_LIMIT
+            && let Some(result) = program.next()
+        {
+            match result {
+                Ok(command) => {
+                    let (line, origin) = program.location().unwrap();
+                    linter.command(line, origin, &command);
+                    count += 1;
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        warnings.extend(linter.finish());
+
+        let mut diagnostics = Vec::new();
+
+        for error in &errors {
+            let (line, columns, here) = Server::place(
+                path.as_deref(),
+                &error.origin,
+                error.line,
+                error.columns.clone(),
+            );
+            let columns = if here { columns } else { 1..line_end(line) };
+            let message = if here {
+                error.message().to_string()
+            } else {
+                error.to_string()
+            };
+
+            diagnostics.push(Json::object(vec![
+                ("range", range(line, columns)),
+                ("severity", SEVERITY_ERROR.into()),
+                ("source", "roboarm_gcode".into()),
+                ("message", message.into()),
+            ]));
+        }
+
+        for warning in &warnings {
+            let (line, _, here) =
+                Server::place(path.as_deref(), &warning.origin, warning.line, 1..1);
+            let message = if here {
+                warning.kind.to_string()
+            } else {
+                warning.to_string()
+            };
+
+            diagnostics.push(Json::object(vec![
+                ("range", range(line, 1..line_end(line))),
+                ("severity", SEVERITY_WARNING.into()),
+                ("source", "roboarm_gcode".into()),
+                ("code", warning.kind.code().into()),
+                ("message", message.into()),
+            ]));
+        }
+
+        diagnostics
+    }
+
+    // Line and 0-based character of a request's position, with the text of
+    // that line
+    fn cursor<'a>(&'a self, uri: &str, params: &Json) -> Option<(usize, usize, &'a str)> {
+        let position = params.get("position")?;
+        let line = position.get("line")?.as_usize()?;
+        let character = position.get("character")?.as_usize()?;
+        let text = self.documents.get(uri)?.lines().nth(line).unwrap_or("");
+
+        let rgcf = uri_path(uri)
+            .as_deref()
+            .and_then(Dialect::from_path)
+            .is_none_or(|dialect| dialect == Dialect::Rgcf);
+
+        rgcf.then_some((line, character, text))
+    }
+
+    fn hover(&self, uri: &str, params: &Json) -> Json {
+        let Some((line, character, text)) = self.cursor(uri, params) else {
+            return Json::Null;
+        };
+        let text = Line::parse(line + 1, text, Dialect::Rgcf);
+
+        // Only the command names itself
+        let Some(term) = text
+            .term_at(character + 1)
+            .filter(|term| text.keyword() == Some(&term.text))
+        else {
+            return Json::Null;
+        };
+        let columns = term.span.columns.clone();
+
+        match hover_text(&term.text) {
+            Some(text) => Json::object(vec![
+                (
+                    "contents",
+                    Json::object(vec![("kind", "markdown".into()), ("value", text.into())]),
+                ),
+                ("range", range(line + 1, columns)),
+            ]),
+            None => Json::Null,
+        }
+    }
+
+    fn complete(&self, uri: &str, params: &Json) -> Json {
+        let Some((_, character, text)) = self.cursor(uri, params) else {
+            return Json::Array(Vec::new());
+        };
+        let before: String = text.chars().take(character).collect();
+        let words: Vec<&str> = before.split_whitespace().collect();
+        let typing = before.ends_with(|c: char| !c.is_whitespace());
+        let index = words.len() - usize::from(typing);
+        let command = words.first().copied().unwrap_or("");
+
+        let path = uri_path(uri);
+        let sources = || {
+            sources(
+                path.as_deref(),
+                self.documents.get(uri).map_or("", String::as_str),
+            )
+        };
+        let defined = |keyword: &str| {
+            let mut names: Vec<String> = sources()
+                .iter()
+                .flat_map(|source| {
+                    source
+                        .text
+                        .lines()
+                        .filter_map(|line| defined_name(line, keyword))
+                })
+                .collect();
+            names.sort();
+            names.dedup();
+            names
+        };
+
+        let items: Vec<Json> = if typing && words.last().is_some_and(|word| word.starts_with('@')) {
+            defined("POSE")
+                .iter()
+                .map(|name| completion(name, KIND_CONSTANT, None))
+                .collect()
+        } else if index == 0 {
+            DOCS.iter()
+                .map(|(name, _)| completion(name, KIND_KEYWORD, usage(name)))
+                .collect()
+        } else if index == 1 && command == "MN" {
+            AXES.iter()
+                .map(|axis| completion(axis, KIND_ENUM_MEMBER, usage(&format!("MN {}", axis))))
+                .collect()
+        } else if index == 1 && command == "UN" {
+            UNITS
+                .iter()
+                .map(|unit| completion(unit, KIND_ENUM_MEMBER, None))
+                .collect()
+        } else if index == 1 && command == "CALL" {
+            defined("SUB")
+                .iter()
+                .map(|name| completion(name, KIND_FUNCTION, None))
+                .collect()
+        } else {
+            let name = match command {
+                "MN" => format!("MN {}", words.get(1).unwrap_or(&"")),
+                _ => command.to_string(),
+            };
+
+            parser::signature(&name)
+                .unwrap_or(&[])
+                .iter()
+                .map(|parameter| completion(&format!("{}=", parameter.name), KIND_FIELD, None))
+                .collect()
+        };
+
+        Json::Array(items)
+    }
+
+    fn definition(&self, uri: &str, params: &Json) -> Json {
+        let Some((_, character, text)) = self.cursor(uri, params) else {
+            return Json::Null;
+        };
+        let path = uri_path(uri);
+        let document = self.documents.get(uri).map_or("", String::as_str);
+
+        if let Some(target) = include_target(text) {
+            let file = match path.as_deref().and_then(Path::parent) {
+                Some(directory) => directory.join(target),
+                None => PathBuf::from(target),
+            };
+            return match file.is_file() {
+                true => location(path_uri(&file), 1),
+                false => Json::Null,
+            };
+        }
+
+        let line = Line::parse(0, text, Dialect::Rgcf);
+        let Some(word) = line.term_at(character + 1) else {
+            return Json::Null;
+        };
+        let first = line.terms().next().is_some_and(|first| first == word);
+
+        let (keyword, name) = match (line.keyword(), word.text.strip_prefix('@')) {
+            (_, Some(pose)) => ("POSE", pose),
+            (Some("CALL"), None) if !first => ("SUB", word.text.as_str()),
+            _ => return Json::Null,
+        };
+        let name = name.to_lowercase();
+
+        for source in sources(path.as_deref(), document) {
+            let found = source
+                .text
+                .lines()
+                .position(|line| defined_name(line, keyword).as_deref() == Some(name.as_str()));
+
+            if let Some(index) = found {
+                let uri = match source.path.as_deref() {
+                    Some(file) if source.path != path => path_uri(file),
+                    _ => uri.to_string(),
+                };
+                return location(uri, index + 1);
+            }
+        }
+
+        Json::Null
+    }
+}
+
+// Serves one client until it exits or closes the stream
+pub fn serve<R: BufRead, W: Write>(mut reader: R, mut writer: W) -> io::Result<()> {
+    let mut server = Server::default();
+
+    while let Some(message) = read_message(&mut reader)? {
+        if message.get("method").and_then(Json::as_str) == Some("exit") {
+            break;
+        }
+
+        for reply in server.handle(&message) {
+            write_message(&mut writer, &reply)?;
+        }
+    }
+
+    Ok(())
+}
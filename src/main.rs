@@ -18,48 +18,375 @@
 // Generate raw motor sequences | Execute motor sequences
 
 use rfd::FileDialog;
-use std::fs::File;
-use std::io::{Read, stdin};
-use std::path::PathBuf;
+use roboarm_gcode::binary;
+use roboarm_gcode::executor::Executor;
+use roboarm_gcode::format;
+use roboarm_gcode::gcode;
+use roboarm_gcode::interpreter::{Interpreter, Lines};
+use roboarm_gcode::kinematics::{self, Arm, Reach, ReachError};
+use roboarm_gcode::lint::{self, Linter, RobotProfile};
+use roboarm_gcode::lsp;
+use roboarm_gcode::parser::Dialect;
+use roboarm_gcode::poses::{self, Poses};
+use roboarm_gcode::serial::{InitError, SerialLink};
+use roboarm_gcode::{Command, ParseError};
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::iter;
+use std::path::Path;
 use std::time::Duration;
 
+// Commands run by `check`, `compile` and the dry run before a job, enough to
+// cover any program that ends
+const CHECK_LIMIT: usize = 1_000_000;
+
+// Key compiled programs are signed with, shared by the machine compiling them
+// and the one running them. Read from the file named by RGCB_KEY.
+fn signing_key() -> Result<Vec<u8>, String> {
+    let path = env::var("RGCB_KEY").map_err(|_| "RGCB_KEY does not name a key file".to_string())?;
+    let key = fs::read(&path).map_err(|e| format!("Failed to read key {}: {}", path, e))?;
+
+    match key.is_empty() {
+        true => Err(format!("Key {} is empty", path)),
+        false => Ok(key),
+    }
+}
+
 fn delimiter(character: &str, length: u8) {
     let mut delimiter_string = String::new();
 
     for _ in 0..length {
-        delimiter_string.push(character.chars().nth(0).unwrap());
+        delimiter_string.push(character.chars().next().unwrap());
     }
 
     println!("{}", delimiter_string);
 }
 
-struct InitError {
-    message: String,
-    error: String,
+// fmt, Rewrites each file in canonical form
+fn format_files(paths: &[String]) {
+    for path in paths.iter().map(Path::new) {
+        let dialect = Dialect::from_path(path).unwrap_or(Dialect::Rgcf);
+
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                println!("[fmt] Failed to read {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let formatted = format::format_program(&source, dialect);
+
+        if formatted == source {
+            continue;
+        }
+
+        match fs::write(path, formatted) {
+            Ok(_) => println!("[fmt] Formatted {}", path.display()),
+            Err(e) => println!("[fmt] Failed to write {}: {}", path.display(), e),
+        }
+    }
+}
+
+// check, Prints parse errors, lint warnings and unreachable targets for each
+// file, one JSON object per line with --json
+fn check_files(paths: &[String]) {
+    let json = paths.first().is_some_and(|flag| flag == "--json");
+    let paths = if json { &paths[1..] } else { paths };
+    let profile = RobotProfile::default();
+    let arm = Arm::default();
+
+    for path in paths.iter().map(Path::new) {
+        let dialect = Dialect::from_path(path).unwrap_or(Dialect::Rgcf);
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                println!("[check] Failed to read {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let (result, pose_library) = match poses::load_beside(path) {
+            Some((_, Err(report))) => (Err(report), None),
+            loaded => {
+                let pose_library = loaded.and_then(|(_, poses)| poses.ok());
+                let program = Interpreter::from_reader(BufReader::new(file), dialect)
+                    .with_path(path)
+                    .with_poses(pose_library.clone().unwrap_or_default());
+
+                (lint::lint(program, &profile, CHECK_LIMIT), pose_library)
+            }
+        };
+        let parsed = result.is_ok();
+
+        match result {
+            Ok(diagnostics) if json => diagnostics
+                .iter()
+                .for_each(|diagnostic| println!("{}", diagnostic.to_json())),
+            Ok(diagnostics) => diagnostics
+                .iter()
+                .for_each(|diagnostic| println!("{}", diagnostic)),
+            Err(report) if json => report
+                .errors
+                .iter()
+                .for_each(|error| println!("{}", lint::error_json(error))),
+            Err(report) => print!("{}", report),
+        }
+
+        // check/reach, Targets are only solved for a program that parses
+        let Some(file) = parsed.then(|| File::open(path).ok()).flatten() else {
+            continue;
+        };
+        let program = Interpreter::from_reader(BufReader::new(file), dialect)
+            .with_path(path)
+            .with_poses(pose_library.unwrap_or_default());
+
+        if let Err(errors) = kinematics::check_reach(program, &arm, &profile, CHECK_LIMIT) {
+            for error in errors {
+                match json {
+                    true => println!("{}", error.to_json()),
+                    false => println!("{}", error),
+                }
+            }
+        }
+    }
+}
+
+// compile, Writes FILE.rgcb next to each program
+fn compile_files(paths: &[String]) {
+    let profile = RobotProfile::default();
+    let key = match signing_key() {
+        Ok(key) => key,
+        Err(e) => {
+            println!("[compile] {}", e);
+            return;
+        }
+    };
+
+    for path in paths.iter().map(Path::new) {
+        let dialect = Dialect::from_path(path).unwrap_or(Dialect::Rgcf);
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                println!("[compile] Failed to read {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let pose_library = match poses::load_beside(path) {
+            Some((_, Err(report))) => {
+                print!("[compile] {}", report);
+                continue;
+            }
+            Some((_, Ok(loaded))) => loaded,
+            None => Poses::new(),
+        };
+
+        let program = Interpreter::from_reader(BufReader::new(file), dialect)
+            .with_path(path)
+            .with_poses(pose_library);
+
+        let bytes = match binary::compile(program, &profile, &Arm::default(), &key, CHECK_LIMIT) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                println!("[compile] {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let output = path.with_extension(binary::EXTENSION);
+
+        match fs::write(&output, bytes) {
+            Ok(_) => println!("[compile] Compiled {}", output.display()),
+            Err(e) => println!("[compile] Failed to write {}: {}", output.display(), e),
+        }
+    }
 }
-impl InitError {
-    fn new(message: &str, error: &str) -> InitError {
-        InitError {
-            message: message.to_string(),
-            error: error.to_string(),
+
+// decompile, Prints each compiled program as .rgcf text
+fn decompile_files(paths: &[String]) {
+    let profile = RobotProfile::default();
+    let key = match signing_key() {
+        Ok(key) => key,
+        Err(e) => {
+            println!("[decompile] {}", e);
+            return;
+        }
+    };
+
+    for path in paths.iter().map(Path::new) {
+        let decompiled = fs::read(path).map_err(|e| e.to_string()).and_then(|bytes| {
+            binary::decompile(&bytes, &profile, &Arm::default(), &key).map_err(|e| e.to_string())
+        });
+
+        match decompiled {
+            Ok(text) => print!("{}", text),
+            Err(e) => println!("[decompile] {}: {}", path.display(), e),
         }
     }
 }
 
+// program/check, Lints each command and solves its target as the program
+// produces it. Lint warnings are printed with `warn` and never stop the
+// program, a target the arm cannot reach is an error.
+fn checked<'a, I: Lines + 'a>(
+    mut program: Interpreter<I>,
+    profile: &'a RobotProfile,
+    arm: &'a Arm,
+    warn: bool,
+) -> impl Iterator<Item = Result<Command, String>> + 'a {
+    let mut linter = Linter::new(profile);
+    let mut reach = Reach::new(arm, profile);
+
+    iter::from_fn(move || {
+        let command = match program.next()? {
+            Ok(command) => command,
+            Err(e) => return Some(Err(e.to_string())),
+        };
+        let (line, origin) = program.location().unwrap();
+
+        linter.command(line, origin, &command);
+        for diagnostic in linter.take() {
+            if warn {
+                println!("[program/lint] {}", diagnostic);
+            }
+        }
+
+        match reach.command(&command) {
+            Ok(()) => Some(Ok(command)),
+            Err(reason) => {
+                let error = ReachError {
+                    line,
+                    origin: origin.clone(),
+                    command,
+                    reason,
+                };
+                Some(Err(error.to_string()))
+            }
+        }
+    })
+}
+
+// program/dry_run, Reports every error before anything is sent. The program is
+// streamed like the job itself, so memory stays bounded; one that keeps
+// running past `CHECK_LIMIT` commands has the rest checked as it is sent.
+fn dry_run<I: Lines>(program: Interpreter<I>, profile: &RobotProfile, arm: &Arm) -> bool {
+    let mut count = 0;
+    let mut errors = Vec::new();
+
+    for result in checked(program, profile, arm, true) {
+        match result {
+            Ok(_) => count += 1,
+            Err(e) => errors.push(e),
+        }
+
+        if count == CHECK_LIMIT {
+            break;
+        }
+    }
+
+    if !errors.is_empty() {
+        delimiter("=", 50);
+        println!("[program/dry_run] {} error(s) found:", errors.len());
+        for error in errors {
+            println!("  {}", error);
+        }
+        return false;
+    }
+
+    match count == CHECK_LIMIT {
+        true => println!(
+            "[program/dry_run] Checked the first {} command(s), program keeps running after them.",
+            count
+        ),
+        false => println!("[program/dry_run] Checked {} command(s).", count),
+    }
+
+    true
+}
+
+// program/execute, Commands are sent as the program produces them
+fn execute_program<E: fmt::Display, I: Iterator<Item = Result<Command, E>>>(
+    link: SerialLink,
+    program: I,
+) {
+    let mut executor = Executor::new(link).with_line_numbers();
+    let mut sent = 0;
+
+    for result in program {
+        let command = match result {
+            Ok(command) => command,
+            Err(e) => {
+                println!("[program/execute] Stopped on error: {}", e);
+                return;
+            }
+        };
+
+        println!("Parsed: {:?}", command);
+
+        if command == Command::WI {
+            println!("[program/execute] Waiting for the arm to finish moving...");
+        }
+
+        if let Err(e) = executor.execute(&command) {
+            println!("[program/execute] Error sending program: {}", e);
+            return;
+        }
+
+        sent += 1;
+    }
+
+    println!("[program/execute] Sent {} command(s).", sent);
+}
+
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.first().is_some_and(|mode| mode == "fmt") {
+        format_files(&args[1..]);
+        return;
+    }
+
+    if args.first().is_some_and(|mode| mode == "compile") {
+        compile_files(&args[1..]);
+        return;
+    }
+
+    if args.first().is_some_and(|mode| mode == "decompile") {
+        decompile_files(&args[1..]);
+        return;
+    }
+
+    if args.first().is_some_and(|mode| mode == "lsp") {
+        if let Err(e) = lsp::serve(io::stdin().lock(), io::stdout().lock()) {
+            eprintln!("[lsp] {}", e);
+        }
+        return;
+    }
+
+    if args.first().is_some_and(|mode| mode == "check") {
+        check_files(&args[1..]);
+        return;
+    }
+
+    // stream, Runs a program piped in on stdin, which cannot be read twice, so
+    // each command is checked just before it is sent instead of in a dry run
+    let stream = args.first().is_some_and(|mode| mode == "stream");
+
     println!("==ROBOTARM GCODE PARSER==");
-    
+
     // PORT ASSOCIATED WITH SERIAL CONNECTION
     let port_address = "/dev/cu.usbserial-210";
 
     // init
     let mut init_errors: Vec<InitError> = Vec::new();
 
-    let mut port = match serialport::new(port_address, 115_200)
-        .timeout(Duration::from_millis(5000))
-        .open()
-    {
-        Ok(serial_port) => serial_port,
+    let mut link = match SerialLink::open(port_address) {
+        Ok(link) => link,
         Err(_) => {
             println!("[init] Failed to open serial port or timed out.");
             return;
@@ -76,205 +403,144 @@ fn main() {
     delimiter("=", 30);
 
     // init/write
-    let init_command = "IN 0";
     println!("[init/write] Sending check string...");
-    let write_result = port.write(init_command.as_bytes());
 
-    match write_result {
+    match link.send_check() {
         Ok(_) => {
             println!("[init/write] Sent check string successfully!");
             println!("[init/write] Waiting for check string read response...");
         }
         Err(e) => {
-            println!("[init/write] Error sending check string: {:?}", e);
-            init_errors.push(InitError::new(
-                "[init/write] Error sending check string.",
-                &e.to_string(),
-            ));
+            println!("[init/write] Error sending check string: {:?}", e.error);
+            init_errors.push(e);
         }
     }
 
     // init/read
-    let mut buf: Vec<u8> = vec![0; 100];
-
-    let read_result = port.read(&mut buf);
-
-    match read_result {
-        Ok(n) => {
-            let check_read_value = String::from_utf8_lossy(&buf[..n]);
+    match link.read_check() {
+        Ok(_) => {
             println!("[init/read] Check string read successfully.");
         }
         Err(e) => {
-            println!(
-                "[init/read] An error occurred trying to read from the device, or the connection timed out. [{}]",
-                e
-            );
-            init_errors.push(InitError::new("[init/read] An error occurred trying to read from the device, or the connection timed out.", &e.to_string()));
+            println!("{} [{}]", e.message, e.error);
+            init_errors.push(e);
         }
     }
 
     // init/verify | Print failed checks
     println!("[init/verify] Failed checks:");
 
-    let mut all_cleared = true;
-
     for error in &init_errors {
         println!("{} | {}", error.error, error.message);
-
-        if (all_cleared) {
-            all_cleared = false;
-        }
     }
 
-    if all_cleared {
+    if init_errors.is_empty() {
         println!("[init/verify] Cleared all checks.");
     }
 
     delimiter("=", 50);
 
     // program
+    let profile = RobotProfile::default();
+    let arm = Arm::default();
 
-    // program/select_file, Select G-Code source file
-    let mut pathbuf = PathBuf::new();
-
-    loop {
-        let file_dialog = FileDialog::new();
-
-        println!("[program/select_file] Select a G-code file...");
-
-        let path_buffer = file_dialog
-            .add_filter("Robot Arm G-Code File", &["rgcf".to_string()])
-            .pick_file();
-
-        match path_buffer {
-            Some(path) => {
-                pathbuf = path;
-                break;
-            }
-            None => {
-                println!("[program/select_file] Canceled file dialog.");
-                return;
-            }
+    if stream {
+        let dialect = match args.get(1).is_some_and(|flag| flag == "--gcode") {
+            true => Dialect::GCode,
+            false => Dialect::Rgcf,
         };
-    }
-
-    // program/read_file
-
-    let mut file_string = String::new();
-    let mut target_file = File::open(pathbuf).expect("[program/select_file] Failed to open file.");
-
-    File::read_to_string(&mut target_file, &mut file_string)
-        .expect("[program/read_file] Failed to read file.");
-
-    println!("File: {}", file_string);
-    println!("[program/select_file] Read file successfully.");
+        let program = Interpreter::from_reader(io::stdin().lock(), dialect);
 
-    // ================
-    // PARSE LOGIC
-    // =================
-
-    #[derive(Debug)]
-    enum Axis {
-        X(f32),
-        Y(f32),
-        Z(f32),
-        A(f32),
-        B(f32, f32),
-        C(f32),
+        println!("[program/stream] Reading the program from stdin...");
+        execute_program(link, checked(program, &profile, &arm, true));
+        return;
     }
 
-    // No Op
-    // Homing
-    // Target
-    // Claw
-    // Manual
-    // Return Home
-    // Reset
-    // Force Stop
-
-    #[derive(Debug)]
-    enum Command {
-        NO,
-        HM(f32, f32, f32),
-        TG(f32, f32, f32),
-        CL(f32, f32, f32, f32, f32),
-        MN(Axis),
-        RH,
-        RS,
-        FS,
-    }
+    // program/select_file, Select G-Code source file
+    println!("[program/select_file] Select a G-code file...");
 
-    fn extract_command(command_string: &str, arguments: Vec<&str>) -> Result<Command, bool> {
-        println!("COmmand string: {}", command_string);
-        match command_string {
-            "NO" => Ok(Command::NO),
-            "HM" => Ok(Command::HM(
-                arguments[0].parse::<f32>().unwrap(),
-                arguments[1].parse::<f32>().unwrap(),
-                arguments[2].parse::<f32>().unwrap(),
-            )),
-            "TG" => Ok(Command::TG(
-                arguments[0].parse::<f32>().unwrap(),
-                arguments[1].parse::<f32>().unwrap(),
-                arguments[2].parse::<f32>().unwrap(),
-            )),
-            "CL" => Ok(Command::CL(
-                arguments[0].parse::<f32>().unwrap(),
-                arguments[1].parse::<f32>().unwrap(),
-                arguments[2].parse::<f32>().unwrap(),
-                arguments[3].parse::<f32>().unwrap(),
-                arguments[4].parse::<f32>().unwrap(),
-            )),
-            "MN" => match arguments[0] {
-                "X" => Ok(Command::MN(Axis::X(arguments[1].parse::<f32>().unwrap()))),
-                "Y" => Ok(Command::MN(Axis::Y(arguments[1].parse::<f32>().unwrap()))),
-                "Z" => Ok(Command::MN(Axis::Z(arguments[1].parse::<f32>().unwrap()))),
-                "A" => Ok(Command::MN(Axis::A(arguments[1].parse::<f32>().unwrap()))),
-                "B" => Ok(Command::MN(Axis::B(
-                    arguments[1].parse::<f32>().unwrap(),
-                    arguments[2].parse::<f32>().unwrap(),
-                ))),
-                "C" => Ok(Command::MN(Axis::C(arguments[1].parse::<f32>().unwrap()))),
-                _ => Err(false),
-            },
-            "RH" => Ok(Command::RH),
-            "RS" => Ok(Command::RS),
-            "FS" => Ok(Command::FS),
-            _ => Result::Err(false),
+    let pathbuf = match FileDialog::new()
+        .add_filter("Robot Arm G-Code File", &["rgcf".to_string()])
+        .add_filter("G-Code File", gcode::EXTENSIONS)
+        .add_filter("Compiled Robot Arm Program", &[binary::EXTENSION])
+        .pick_file()
+    {
+        Some(path) => path,
+        None => {
+            println!("[program/select_file] Canceled file dialog.");
+            return;
         }
-    }
-
-    let commands = file_string.split("\n").collect::<Vec<&str>>();
-
-    for command in commands {
-        let terms = command.split_whitespace().collect::<Vec<&str>>();
-
-        println!("Terms: {:?}", terms);
-
-        println!("Function: {}", terms[0]);
-
-        print!("Arguments: ");
-
-        let mut args: Vec<&str> = Vec::new();
+    };
 
-        for arg_idx in 1..terms.len() {
-            if arg_idx == terms.len() - 1 {
-                print!("{}\n", terms[arg_idx]);
-                break;
+    // program/load, Only files signed with our key are run, and their limits
+    // and targets are checked again before anything is sent
+    if pathbuf
+        .extension()
+        .is_some_and(|extension| extension == binary::EXTENSION)
+    {
+        let bytes = fs::read(&pathbuf).expect("[program/load] Failed to read file.");
+        let decoded = signing_key().and_then(|key| {
+            binary::decode(&bytes, &profile, &arm, &key).map_err(|e| e.to_string())
+        });
+
+        match decoded {
+            Ok(commands) => {
+                println!("[program/load] Loaded {} command(s).", commands.len());
+                execute_program(link, commands.into_iter().map(Ok::<_, ParseError>));
             }
-            print!("{},", terms[arg_idx]);
-            args.push(terms[arg_idx]);
+            Err(e) => println!("[program/load] {}", e),
         }
+        return;
+    }
 
-        let parsed = extract_command(terms[0], args);
+    // program/read_file, Read a line at a time, so long programs are never
+    // held in memory as a whole
+    let dialect = Dialect::from_path(&pathbuf).unwrap_or(Dialect::Rgcf);
+    let open_file = || {
+        File::open(&pathbuf)
+            .map(BufReader::new)
+            .expect("[program/read_file] Failed to read file.")
+    };
 
-        match &parsed {
-            Ok(result) => {
-                println!("Parsed: {:?}", parsed);
+    println!("File: {}", pathbuf.display());
+    println!("[program/select_file] Opened file successfully.");
+
+    // program/poses, Shared poses kept next to the job
+    let mut pose_library = Poses::new();
+
+    if let Some((pose_path, loaded)) = poses::load_beside(&pathbuf) {
+        match loaded {
+            Ok(loaded) => {
+                println!(
+                    "[program/poses] Loaded {} pose(s) from {}.",
+                    loaded.len(),
+                    pose_path.display()
+                );
+                pose_library = loaded;
             }
-            Err(e) => {
-                println!("Error: {}", e);
+            Err(report) => {
+                delimiter("=", 50);
+                print!("[program/poses] {}", report);
+                return;
             }
         }
     }
+
+    // program/dry_run, Parse errors, lint warnings and unreachable targets of
+    // the whole program are reported before the arm moves
+    let program = Interpreter::from_reader(open_file(), dialect)
+        .with_path(&pathbuf)
+        .with_poses(pose_library.clone());
+
+    if !dry_run(program, &profile, &arm) {
+        return;
+    }
+
+    // program/execute, The file is read again and checked as it is sent, in
+    // case it changed since the dry run. Warnings were printed by the dry run.
+    let program = Interpreter::from_reader(open_file(), dialect)
+        .with_path(&pathbuf)
+        .with_poses(pose_library);
+
+    execute_program(link, checked(program, &profile, &arm, false));
 }
@@ -0,0 +1,60 @@
+// FEED RATE AND ACCELERATION
+// ==========================
+// FR rate        Feed rate for the moves that follow (length units/min)
+// AC rate        Acceleration for the moves that follow (length units/s²)
+// TG ... F= ACC= Overrides for a single move, also on TR and MN
+//
+// Both are modal and sent to the Arduino as FR/AC commands. A move with an
+// override is wrapped in the overriding values and the modal ones after it.
+// Overrides are in the length units, like the modal values they stand in for.
+
+use crate::command::Command;
+
+// Firmware values in effect until a program sets its own
+pub const FEED_RATE: f32 = 1000.0;
+pub const ACCELERATION: f32 = 500.0;
+
+// F= and ACC= given on a single move
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Overrides {
+    pub feed: Option<f32>,
+    pub accel: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Motion {
+    pub feed: f32,
+    pub accel: f32,
+}
+impl Default for Motion {
+    fn default() -> Self {
+        Motion {
+            feed: FEED_RATE,
+            accel: ACCELERATION,
+        }
+    }
+}
+impl Motion {
+    pub fn track(&mut self, command: &Command) {
+        match command {
+            Command::FR(feed) => self.feed = *feed,
+            Command::AC(accel) => self.accel = *accel,
+            _ => {}
+        }
+    }
+
+    // `command` run at the given feed rate and acceleration, leaving the
+    // modal values as they were
+    pub fn with_overrides(&self, command: Command, overrides: Overrides) -> Vec<Command> {
+        let feed = overrides.feed.filter(|feed| *feed != self.feed);
+        let accel = overrides.accel.filter(|accel| *accel != self.accel);
+
+        let mut commands: Vec<Command> = feed.map(Command::FR).into_iter().collect();
+        commands.extend(accel.map(Command::AC));
+        commands.push(command);
+        commands.extend(feed.map(|_| Command::FR(self.feed)));
+        commands.extend(accel.map(|_| Command::AC(self.accel)));
+
+        commands
+    }
+}
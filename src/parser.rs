@@ -0,0 +1,1085 @@
+// COMMAND | ARG ARG ARG
+// 1. Read command
+// 2. Scan for argument count
+// 3. Extract arguments
+
+use crate::command::{
+    Axis, CLAW_ACCEL, CLAW_FORCE, CLAW_HOLD, CLAW_SPEED, Command, TOOL_PITCH, TOOL_ROLL, TOOL_YAW,
+};
+use crate::error::{ParseError, ParseErrorKind, ParseReport};
+use crate::expr::{self, ExprError, Variables};
+use crate::gcode;
+use crate::interpreter::Interpreter;
+use crate::motion::{Motion, Overrides};
+use crate::poses::Poses;
+use crate::position::Position;
+use crate::units::{Unit, Units};
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Rgcf,
+    GCode,
+}
+impl Dialect {
+    pub fn from_path(path: &Path) -> Option<Dialect> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        if extension == "rgcf" {
+            Some(Dialect::Rgcf)
+        } else if gcode::EXTENSIONS.contains(&extension.as_str()) {
+            Some(Dialect::GCode)
+        } else {
+            None
+        }
+    }
+}
+
+// A whitespace separated term of a program line, with the columns it occupies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub column: usize,
+}
+impl<'a> Token<'a> {
+    pub fn columns(&self) -> Range<usize> {
+        self.column..self.column + self.text.chars().count()
+    }
+}
+
+// Marlin style checksum, the XOR of every byte of the line before the `*`
+pub fn checksum(text: &str) -> u8 {
+    text.bytes().fold(0, |checksum, byte| checksum ^ byte)
+}
+
+// Byte offset of a column of `source`
+fn offset(source: &str, column: usize) -> usize {
+    source
+        .char_indices()
+        .nth(column - 1)
+        .map(|(offset, _)| offset)
+        .unwrap_or(source.len())
+}
+
+// `N<number> ... *<checksum>` around a line. The checksum is checked and both
+// are blanked out, so the columns of the rest of the line do not move. Only a
+// line with a line number has its checksum read, as `*` also multiplies.
+pub fn strip_line_number(line: usize, source: &str) -> Result<String, ParseError> {
+    split_line_number(line, source).map(|(stripped, _)| stripped)
+}
+
+// Order of the `N` line numbers of a file as it is read: each one has to be
+// greater than the last. Lines without a number can come in between.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LineNumbers {
+    last: Option<u64>,
+}
+impl LineNumbers {
+    // `strip_line_number` of the next line read from the file
+    pub fn strip(&mut self, line: usize, source: &str) -> Result<String, ParseError> {
+        let (stripped, number) = split_line_number(line, source)?;
+
+        let Some((number, token)) = number else {
+            return Ok(stripped);
+        };
+
+        if let Some(last) = self.last.filter(|last| number <= *last) {
+            return Err(ParseError::new(
+                line,
+                &token,
+                ParseErrorKind::LineNumberOrder(last),
+            ));
+        }
+
+        self.last = Some(number);
+        Ok(stripped)
+    }
+}
+
+// The `N<digits>` line number at the start of a line's terms and, on such a
+// line only, the `*<digits>` checksum at the end of the last one, as `*` also
+// multiplies
+pub fn line_number_tokens<'a>(terms: &[Token<'a>]) -> Option<(Token<'a>, Option<Token<'a>>)> {
+    let first = terms.first()?;
+    let digits = first
+        .text
+        .strip_prefix('N')?
+        .chars()
+        .take_while(|character| character.is_ascii_digit())
+        .count();
+
+    if digits == 0 {
+        return None;
+    }
+
+    let number = Token {
+        text: &first.text[..1 + digits],
+        column: first.column,
+    };
+
+    let last = terms.last().unwrap();
+    let checksum = last
+        .text
+        .rsplit_once('*')
+        .filter(|(_, given)| {
+            !given.is_empty() && given.chars().all(|character| character.is_ascii_digit())
+        })
+        .map(|(before, _)| Token {
+            text: &last.text[before.len()..],
+            column: last.column + before.chars().count(),
+        });
+
+    Some((number, checksum))
+}
+
+// `strip_line_number`, along with the line number and its token
+fn split_line_number(
+    line: usize,
+    source: &str,
+) -> Result<(String, Option<(u64, Token<'_>)>), ParseError> {
+    let tokens = tokenize(line, source)?;
+
+    let Some((token, given)) = line_number_tokens(&tokens) else {
+        return Ok((source.to_string(), None));
+    };
+
+    let number = token.text[1..]
+        .parse::<u64>()
+        .map_err(|_| ParseError::new(line, &token, ParseErrorKind::BadNumber))?;
+
+    let start = offset(source, token.column);
+    let mut stripped = source.to_string();
+    stripped.replace_range(
+        start..start + token.text.len(),
+        &" ".repeat(token.text.len()),
+    );
+
+    let Some(given) = given else {
+        return Ok((stripped, Some((number, token))));
+    };
+
+    let star = offset(source, given.column);
+    let expected = checksum(&source[start..star]);
+
+    if given.text[1..].parse::<u32>().ok() != Some(expected as u32) {
+        return Err(ParseError::new(
+            line,
+            &given,
+            ParseErrorKind::BadChecksum(expected),
+        ));
+    }
+
+    stripped.replace_range(star..star + given.text.len(), &" ".repeat(given.text.len()));
+
+    Ok((stripped, Some((number, token))))
+}
+
+// How a comment is written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommentKind {
+    // `( inline )`
+    Inline,
+    // `( inline` running to the end of the line
+    Unterminated,
+    // `; to end of line`
+    ToEnd,
+}
+
+// A term or comment of a line. Comments keep their delimiters and anything
+// after them up to the end of the line; `attached` when written against the
+// term before them, with no space between.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lexeme<'a> {
+    Term(Token<'a>),
+    Comment {
+        token: Token<'a>,
+        kind: CommentKind,
+        attached: bool,
+    },
+}
+
+// Splits a line into terms and comments, the one lexer behind `tokenize` and
+// `ast::Line`. Whitespace includes a trailing `\r` left over from Windows line
+// endings. A bracketed expression stays a single term, spaces and parentheses
+// included.
+pub fn lex(source: &str) -> Vec<Lexeme<'_>> {
+    let mut lexemes = Vec::new();
+    let mut start: Option<(usize, usize)> = None;
+    let mut comment: Option<(usize, usize, bool)> = None;
+    let mut depth = 0;
+
+    for (column, (offset, character)) in source.char_indices().enumerate() {
+        let column = column + 1;
+
+        if let Some((comment_offset, comment_column, attached)) = comment {
+            if character == ')' {
+                lexemes.push(Lexeme::Comment {
+                    token: Token {
+                        text: &source[comment_offset..=offset],
+                        column: comment_column,
+                    },
+                    kind: CommentKind::Inline,
+                    attached,
+                });
+                comment = None;
+            }
+            continue;
+        }
+
+        let separator =
+            depth == 0 && (character.is_whitespace() || character == ';' || character == '(');
+        let attached = separator && start.is_some();
+
+        match (separator, start) {
+            (false, None) => start = Some((offset, column)),
+            (true, Some((token_offset, token_column))) => {
+                lexemes.push(Lexeme::Term(Token {
+                    text: &source[token_offset..offset],
+                    column: token_column,
+                }));
+                start = None;
+            }
+            _ => {}
+        }
+
+        match character {
+            ';' if depth == 0 => {
+                lexemes.push(Lexeme::Comment {
+                    token: Token {
+                        text: &source[offset..],
+                        column,
+                    },
+                    kind: CommentKind::ToEnd,
+                    attached,
+                });
+                return lexemes;
+            }
+            '(' if depth == 0 => comment = Some((offset, column, attached)),
+            '[' => depth += 1,
+            ']' => depth = usize::saturating_sub(depth, 1),
+            _ => {}
+        }
+    }
+
+    if let Some((comment_offset, comment_column, attached)) = comment {
+        lexemes.push(Lexeme::Comment {
+            token: Token {
+                text: &source[comment_offset..],
+                column: comment_column,
+            },
+            kind: CommentKind::Unterminated,
+            attached,
+        });
+    } else if let Some((token_offset, token_column)) = start {
+        lexemes.push(Lexeme::Term(Token {
+            text: &source[token_offset..],
+            column: token_column,
+        }));
+    }
+
+    lexemes
+}
+
+// The terms of a line, its comments dropped
+pub fn tokenize(line: usize, source: &str) -> Result<Vec<Token<'_>>, ParseError> {
+    let mut tokens = Vec::new();
+
+    for lexeme in lex(source) {
+        match lexeme {
+            Lexeme::Term(token) => tokens.push(token),
+            Lexeme::Comment {
+                token,
+                kind: CommentKind::Unterminated,
+                ..
+            } => {
+                return Err(ParseError::new(
+                    line,
+                    &token,
+                    ParseErrorKind::UnterminatedComment,
+                ));
+            }
+            Lexeme::Comment { .. } => {}
+        }
+    }
+
+    Ok(tokens)
+}
+
+// Places an error found inside an expression at its columns in the line
+fn expression_error(line: usize, token: &Token, error: ExprError) -> ParseError {
+    let length = token.text.chars().count();
+    let end = error.span.end.min(length).max(error.span.start);
+
+    ParseError {
+        line,
+        columns: token.column + error.span.start..token.column + end,
+        token: token
+            .text
+            .chars()
+            .skip(error.span.start)
+            .take(end - error.span.start)
+            .collect(),
+        kind: error.kind,
+        origin: Box::default(),
+    }
+}
+
+fn parse_number(line: usize, token: &Token, variables: &Variables) -> Result<f32, ParseError> {
+    if expr::is_expression(token.text) {
+        return expr::parse(token.text)
+            .and_then(|expr| expr::evaluate(&expr, variables))
+            .map_err(|e| expression_error(line, token, e));
+    }
+
+    // `NaN`, `inf` and overflows such as `1e39` parse as f32 but mean nothing
+    // to the arm
+    token
+        .text
+        .parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| ParseError::new(line, token, ParseErrorKind::BadNumber))
+}
+
+// A command argument, which can be given positionally or as `NAME=value`.
+// Parameters without a default must always be given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub default: Option<f32>,
+}
+
+const fn required(name: &'static str) -> Parameter {
+    Parameter {
+        name,
+        default: None,
+    }
+}
+
+const fn optional(name: &'static str, default: f32) -> Parameter {
+    Parameter {
+        name,
+        default: Some(default),
+    }
+}
+
+// Parameters taken by each command, in positional order. `MN` is listed per
+// axis and counts the values following the axis letter, `POSE` the values
+// following the name.
+pub const SIGNATURES: &[(&str, &[Parameter])] = &[
+    ("NO", &[]),
+    ("HM", &[required("X"), required("Y"), required("Z")]),
+    ("TG", &[required("X"), required("Y"), required("Z")]),
+    (
+        "TP",
+        &[
+            required("X"),
+            required("Y"),
+            required("Z"),
+            optional("ROLL", TOOL_ROLL),
+            optional("PITCH", TOOL_PITCH),
+            optional("YAW", TOOL_YAW),
+        ],
+    ),
+    (
+        "TR",
+        &[optional("X", 0.0), optional("Y", 0.0), optional("Z", 0.0)],
+    ),
+    ("POSE", &[required("X"), required("Y"), required("Z")]),
+    ("AP", &[]),
+    ("RP", &[]),
+    (
+        "CL",
+        &[
+            required("OPEN"),
+            optional("FORCE", CLAW_FORCE),
+            optional("SPEED", CLAW_SPEED),
+            optional("ACCEL", CLAW_ACCEL),
+            optional("HOLD", CLAW_HOLD),
+        ],
+    ),
+    ("DW", &[required("MS")]),
+    ("WI", &[]),
+    ("MN X", &[required("ANGLE")]),
+    ("MN Y", &[required("ANGLE")]),
+    ("MN Z", &[required("ANGLE")]),
+    ("MN A", &[required("ANGLE")]),
+    ("MN B", &[required("PITCH"), required("ROLL")]),
+    ("MN C", &[required("ANGLE")]),
+    ("RH", &[]),
+    ("RS", &[]),
+    ("FS", &[]),
+    ("FR", &[required("RATE")]),
+    ("AC", &[required("RATE")]),
+];
+
+pub fn signature(name: &str) -> Option<&'static [Parameter]> {
+    SIGNATURES
+        .iter()
+        .find(|(entry, _)| *entry == name)
+        .map(|(_, parameters)| *parameters)
+}
+
+pub fn arity(name: &str) -> Option<usize> {
+    signature(name).map(|parameters| parameters.len())
+}
+
+// Spans the command and all of its arguments
+fn command_error(
+    line: usize,
+    name: &str,
+    command: &Token,
+    arguments: &[Token],
+    kind: ParseErrorKind,
+) -> ParseError {
+    let end = arguments.last().unwrap_or(command).columns().end;
+
+    ParseError {
+        line,
+        columns: command.column..end,
+        token: name.to_string(),
+        kind,
+        origin: Box::default(),
+    }
+}
+
+pub(crate) fn arity_error(
+    line: usize,
+    name: &str,
+    command: &Token,
+    arguments: &[Token],
+    expected: usize,
+) -> ParseError {
+    let kind = ParseErrorKind::WrongArity {
+        expected,
+        found: arguments.len(),
+    };
+
+    command_error(line, name, command, arguments, kind)
+}
+
+// `NAME=value`, telling it apart from expressions such as `[#a == 1]`
+fn named_argument(text: &str) -> Option<(&str, &str)> {
+    text.split_once('=')
+        .filter(|(key, _)| expr::is_identifier(key))
+}
+
+// Matches the arguments against the command's signature and parses each one
+// as a number. The legacy positional form must give every argument, except
+// for `TP`, which has no legacy form and can leave out the trailing part of
+// its orientation; once a named argument is used the remaining ones can come
+// in any order and fall back to their defaults.
+fn numbers(
+    line: usize,
+    name: &str,
+    command: &Token,
+    arguments: &[Token],
+    variables: &Variables,
+) -> Result<Vec<f32>, ParseError> {
+    let parameters = signature(name).unwrap_or(&[]);
+
+    if !arguments
+        .iter()
+        .any(|argument| named_argument(argument.text).is_some())
+    {
+        let least = match name {
+            "TP" => parameters
+                .iter()
+                .filter(|parameter| parameter.default.is_none())
+                .count(),
+            _ => parameters.len(),
+        };
+
+        if arguments.len() < least || arguments.len() > parameters.len() {
+            let expected = match arguments.len() < least {
+                true => least,
+                false => parameters.len(),
+            };
+            return Err(arity_error(line, name, command, arguments, expected));
+        }
+
+        return parameters
+            .iter()
+            .enumerate()
+            .map(|(index, parameter)| match arguments.get(index) {
+                Some(argument) => parse_number(line, argument, variables),
+                None => Ok(parameter.default.unwrap()),
+            })
+            .collect();
+    }
+
+    let mut values: Vec<Option<f32>> = vec![None; parameters.len()];
+    let mut named = false;
+
+    for argument in arguments {
+        let Some((key, value)) = named_argument(argument.text) else {
+            if named {
+                return Err(ParseError::new(
+                    line,
+                    argument,
+                    ParseErrorKind::PositionalAfterNamed,
+                ));
+            }
+
+            let position = values.iter().flatten().count();
+
+            if position == parameters.len() {
+                return Err(arity_error(
+                    line,
+                    name,
+                    command,
+                    arguments,
+                    parameters.len(),
+                ));
+            }
+
+            values[position] = Some(parse_number(line, argument, variables)?);
+            continue;
+        };
+
+        named = true;
+
+        let key_token = Token {
+            text: key,
+            column: argument.column,
+        };
+        let Some(index) = parameters
+            .iter()
+            .position(|parameter| parameter.name.eq_ignore_ascii_case(key))
+        else {
+            return Err(ParseError::new(
+                line,
+                &key_token,
+                ParseErrorKind::UnknownParameter,
+            ));
+        };
+
+        if values[index].is_some() {
+            return Err(ParseError::new(
+                line,
+                &key_token,
+                ParseErrorKind::DuplicateParameter,
+            ));
+        }
+
+        let value = Token {
+            text: value,
+            column: argument.column + key.chars().count() + 1,
+        };
+        values[index] = Some(parse_number(line, &value, variables)?);
+    }
+
+    parameters
+        .iter()
+        .zip(values)
+        .map(|(parameter, value)| {
+            value.or(parameter.default).ok_or_else(|| {
+                let kind = ParseErrorKind::MissingParameter(parameter.name.to_string());
+                command_error(line, name, command, arguments, kind)
+            })
+        })
+        .collect()
+}
+
+// Feed rates and accelerations have to be positive
+fn rate(
+    line: usize,
+    name: &str,
+    command: &Token,
+    arguments: &[Token],
+    variables: &Variables,
+) -> Result<f32, ParseError> {
+    let rate = numbers(line, name, command, arguments, variables)?[0];
+
+    if rate <= 0.0 {
+        return Err(command_error(
+            line,
+            name,
+            command,
+            arguments,
+            ParseErrorKind::NotPositive,
+        ));
+    }
+
+    Ok(rate)
+}
+
+// Takes the F= and ACC= overrides out of a move's arguments
+fn overrides<'a>(
+    line: usize,
+    arguments: &[Token<'a>],
+    variables: &Variables,
+) -> Result<(Vec<Token<'a>>, Overrides), ParseError> {
+    let mut rest = Vec::new();
+    let mut overrides = Overrides::default();
+
+    for argument in arguments {
+        let Some((key, value)) = named_argument(argument.text) else {
+            rest.push(*argument);
+            continue;
+        };
+
+        let slot = match key.to_ascii_uppercase().as_str() {
+            "F" => &mut overrides.feed,
+            "ACC" => &mut overrides.accel,
+            _ => {
+                rest.push(*argument);
+                continue;
+            }
+        };
+
+        let key_token = Token {
+            text: key,
+            column: argument.column,
+        };
+
+        if slot.is_some() {
+            return Err(ParseError::new(
+                line,
+                &key_token,
+                ParseErrorKind::DuplicateParameter,
+            ));
+        }
+
+        let value = Token {
+            text: value,
+            column: argument.column + key.chars().count() + 1,
+        };
+        let rate = parse_number(line, &value, variables)?;
+
+        if rate <= 0.0 {
+            return Err(ParseError::new(line, &value, ParseErrorKind::NotPositive));
+        }
+
+        *slot = Some(rate);
+    }
+
+    Ok((rest, overrides))
+}
+
+pub fn extract_command(
+    line: usize,
+    command: &Token,
+    arguments: &[Token],
+    variables: &Variables,
+) -> Result<Command, ParseError> {
+    match command.text {
+        "NO" => numbers(line, "NO", command, arguments, variables).map(|_| Command::NO),
+        "HM" => {
+            let n = numbers(line, "HM", command, arguments, variables)?;
+            Ok(Command::HM(n[0], n[1], n[2]))
+        }
+        "TG" => {
+            let n = numbers(line, "TG", command, arguments, variables)?;
+            Ok(Command::TG(n[0], n[1], n[2]))
+        }
+        "TP" => {
+            let n = numbers(line, "TP", command, arguments, variables)?;
+            Ok(Command::TP(n[0], n[1], n[2], n[3], n[4], n[5]))
+        }
+        "CL" => {
+            let n = numbers(line, "CL", command, arguments, variables)?;
+            Ok(Command::CL(n[0], n[1], n[2], n[3], n[4]))
+        }
+        "DW" => {
+            let n = numbers(line, "DW", command, arguments, variables)?;
+
+            if n[0] < 0.0 {
+                return Err(command_error(
+                    line,
+                    "DW",
+                    command,
+                    arguments,
+                    ParseErrorKind::Negative,
+                ));
+            }
+
+            Ok(Command::DW(n[0]))
+        }
+        "MN" => {
+            let Some(axis) = arguments.first() else {
+                return Err(arity_error(line, "MN", command, arguments, 2));
+            };
+            let name = format!("MN {}", axis.text);
+            let values = &arguments[1..];
+
+            match axis.text {
+                "X" => Ok(Command::MN(Axis::X(
+                    numbers(line, &name, command, values, variables)?[0],
+                ))),
+                "Y" => Ok(Command::MN(Axis::Y(
+                    numbers(line, &name, command, values, variables)?[0],
+                ))),
+                "Z" => Ok(Command::MN(Axis::Z(
+                    numbers(line, &name, command, values, variables)?[0],
+                ))),
+                "A" => Ok(Command::MN(Axis::A(
+                    numbers(line, &name, command, values, variables)?[0],
+                ))),
+                "B" => {
+                    let n = numbers(line, &name, command, values, variables)?;
+                    Ok(Command::MN(Axis::B(n[0], n[1])))
+                }
+                "C" => Ok(Command::MN(Axis::C(
+                    numbers(line, &name, command, values, variables)?[0],
+                ))),
+                _ => Err(ParseError::new(line, axis, ParseErrorKind::UnknownAxis)),
+            }
+        }
+        "RH" => numbers(line, "RH", command, arguments, variables).map(|_| Command::RH),
+        "RS" => numbers(line, "RS", command, arguments, variables).map(|_| Command::RS),
+        "FS" => numbers(line, "FS", command, arguments, variables).map(|_| Command::FS),
+        "WI" => numbers(line, "WI", command, arguments, variables).map(|_| Command::WI),
+        "FR" => rate(line, "FR", command, arguments, variables).map(Command::FR),
+        "AC" => rate(line, "AC", command, arguments, variables).map(Command::AC),
+        _ => Err(ParseError::new(
+            line,
+            command,
+            ParseErrorKind::UnknownMnemonic,
+        )),
+    }
+}
+
+// `%rgcf` or `%gcode` on a line of its own switches the dialect for the rest of
+// the file. A bare `%` (tape marker in G-code files) is ignored.
+fn dialect_directive(line: usize, source: &str) -> Option<Result<Dialect, ParseError>> {
+    let trimmed = source.trim();
+    let name = trimmed.strip_prefix('%')?.trim();
+    let token = Token {
+        text: trimmed,
+        column: source.chars().take_while(|c| c.is_whitespace()).count() + 1,
+    };
+
+    match name.to_ascii_lowercase().as_str() {
+        "" => None,
+        "rgcf" => Some(Ok(Dialect::Rgcf)),
+        "gcode" => Some(Ok(Dialect::GCode)),
+        _ => Some(Err(ParseError::new(
+            line,
+            &token,
+            ParseErrorKind::UnknownDialect,
+        ))),
+    }
+}
+
+// Tokens are joined back with single spaces so `#x = 1`, `#x=1` and
+// `#x = #y + 1` all read the same. Keeps where each token starts in the joined
+// text to map expression errors back onto the line.
+struct Joined<'a, 'b> {
+    text: String,
+    starts: Vec<(usize, &'b Token<'a>)>,
+}
+impl<'a, 'b> Joined<'a, 'b> {
+    fn new(tokens: &'b [Token<'a>]) -> Joined<'a, 'b> {
+        let mut text = String::new();
+        let mut starts = Vec::new();
+
+        for token in tokens {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            starts.push((text.chars().count(), token));
+            text.push_str(token.text);
+        }
+
+        Joined { text, starts }
+    }
+
+    // `offset` is the number of characters of the joined text before `error`
+    fn error(&self, line: usize, offset: usize, error: ExprError) -> ParseError {
+        let start = offset + error.span.start;
+        let (token_start, token) = self
+            .starts
+            .iter()
+            .rev()
+            .find(|(token_start, _)| *token_start <= start)
+            .copied()
+            .unwrap_or(self.starts[0]);
+        let rest: String = self.text.chars().skip(start).collect();
+        let token = Token {
+            text: &rest,
+            column: token.column + start - token_start,
+        };
+        let span = 0..error.span.end - error.span.start;
+
+        expression_error(line, &token, ExprError { span, ..error })
+    }
+}
+
+// Carries state from line to line: the active dialect, variables, poses,
+// units, the commanded position and motion settings, shared by both dialects
+pub struct Parser {
+    pub dialect: Dialect,
+    pub variables: Variables,
+    pub poses: Poses,
+    pub units: Units,
+    pub position: Position,
+    pub motion: Motion,
+    // An M2/M30 was run, no line after it is
+    pub ended: bool,
+}
+impl Parser {
+    pub fn new(dialect: Dialect) -> Parser {
+        Parser {
+            dialect,
+            variables: Variables::new(),
+            poses: Poses::new(),
+            units: Units::default(),
+            position: Position::default(),
+            motion: Motion::default(),
+            ended: false,
+        }
+    }
+
+    // Lowers a line into the commands it produces, which may be none
+    pub fn parse_line(&mut self, line: usize, source: &str) -> Result<Vec<Command>, ParseError> {
+        let source = &strip_line_number(line, source)?;
+
+        if source.trim_start().starts_with('%') {
+            if let Some(selected) = dialect_directive(line, source) {
+                self.dialect = selected?;
+            }
+            return Ok(Vec::new());
+        }
+
+        let commands: Vec<Command> = match self.dialect {
+            Dialect::Rgcf => match self.parse_rgcf(line, source)? {
+                Some((command, overrides)) => self.motion.with_overrides(command, overrides),
+                None => Vec::new(),
+            },
+            Dialect::GCode => gcode::parse_line(
+                line,
+                source,
+                &mut self.units,
+                &mut self.position,
+                &self.motion,
+                &mut self.ended,
+            )?,
+        };
+
+        for command in &commands {
+            self.position.track(command);
+            self.motion.track(command);
+        }
+
+        Ok(commands)
+    }
+
+    // The command on an .rgcf line, with the overrides it should run under
+    fn parse_rgcf(
+        &mut self,
+        line: usize,
+        source: &str,
+    ) -> Result<Option<(Command, Overrides)>, ParseError> {
+        let terms = tokenize(line, source)?;
+
+        let Some(first) = terms.first() else {
+            return Ok(None);
+        };
+
+        if first.text.starts_with('#') {
+            self.assign(line, &terms)?;
+            return Ok(None);
+        }
+
+        if first.text == "UN" {
+            self.set_unit(line, &terms)?;
+            return Ok(None);
+        }
+
+        if first.text == "POSE" {
+            self.define_pose(line, &terms)?;
+            return Ok(None);
+        }
+
+        let (arguments, mut overrides) = match first.text {
+            "TG" | "TP" | "TR" | "MN" => overrides(line, &terms[1..], &self.variables)?,
+            _ => (terms[1..].to_vec(), Overrides::default()),
+        };
+
+        let command = match first.text {
+            "AP" | "RP" => {
+                numbers(line, first.text, first, &arguments, &self.variables)?;
+                self.position.relative = first.text == "RP";
+                return Ok(None);
+            }
+            "TR" => {
+                let n = numbers(line, "TR", first, &arguments, &self.variables)?;
+                let [x, y, z] = self.target(line, first, [n[0], n[1], n[2]], true)?;
+                Command::TG(x, y, z)
+            }
+            "TG" if arguments.first().is_some_and(|a| a.text.starts_with('@')) => {
+                self.pose(line, first, &arguments)?
+            }
+            _ => match extract_command(line, first, &arguments, &self.variables)? {
+                Command::TG(x, y, z) => {
+                    let [x, y, z] = self.target(line, first, [x, y, z], self.position.relative)?;
+                    Command::TG(x, y, z)
+                }
+                Command::TP(x, y, z, roll, pitch, yaw) => {
+                    let [x, y, z] = self.target(line, first, [x, y, z], self.position.relative)?;
+                    let [roll, pitch, yaw] =
+                        [roll, pitch, yaw].map(|angle| self.units.orientation(angle));
+                    Command::TP(x, y, z, roll, pitch, yaw)
+                }
+                command => self.units.canonical(command),
+            },
+        };
+
+        // Sent as FR/AC, so converted like the modal values
+        overrides.feed = overrides.feed.map(|feed| self.units.length(feed));
+        overrides.accel = overrides.accel.map(|accel| self.units.length(accel));
+
+        Ok(Some((command, overrides)))
+    }
+
+    // Lowers target values in the current units into an absolute position
+    fn target(
+        &self,
+        line: usize,
+        command: &Token,
+        values: [f32; 3],
+        relative: bool,
+    ) -> Result<[f32; 3], ParseError> {
+        let values = values.map(|value| Some(self.units.length(value)));
+
+        self.position
+            .point(values, relative)
+            .ok_or_else(|| ParseError::new(line, command, ParseErrorKind::UnknownPosition))
+    }
+
+    // POSE name X Y Z
+    fn define_pose(&mut self, line: usize, terms: &[Token]) -> Result<(), ParseError> {
+        let Some(name) = terms.get(1) else {
+            return Err(arity_error(line, "POSE", &terms[0], &[], 4));
+        };
+
+        if !expr::is_identifier(name.text) {
+            return Err(ParseError::new(
+                line,
+                name,
+                ParseErrorKind::BadExpression("invalid pose name".to_string()),
+            ));
+        }
+
+        let n = numbers(line, "POSE", &terms[0], &terms[2..], &self.variables)?;
+        let target = [n[0], n[1], n[2]].map(|value| self.units.length(value));
+        self.poses.insert(name.text.to_lowercase(), target);
+
+        Ok(())
+    }
+
+    // TG @name
+    fn pose(
+        &self,
+        line: usize,
+        command: &Token,
+        arguments: &[Token],
+    ) -> Result<Command, ParseError> {
+        if arguments.len() != 1 {
+            return Err(arity_error(line, "TG", command, arguments, 1));
+        }
+
+        let name = &arguments[0];
+        let Some([x, y, z]) = self.poses.get(&name.text[1..].to_lowercase()) else {
+            return Err(ParseError::new(line, name, ParseErrorKind::UndefinedPose));
+        };
+
+        Ok(Command::TG(*x, *y, *z))
+    }
+
+    // UN MM|IN|DEG|RAD|STEP
+    fn set_unit(&mut self, line: usize, terms: &[Token]) -> Result<(), ParseError> {
+        if terms.len() != 2 {
+            return Err(arity_error(line, "UN", &terms[0], &terms[1..], 1));
+        }
+
+        let unit = Unit::from_name(terms[1].text)
+            .ok_or_else(|| ParseError::new(line, &terms[1], ParseErrorKind::UnknownUnit))?;
+        self.units.set(unit);
+
+        Ok(())
+    }
+
+    // Evaluates an expression spread over several tokens, e.g. the condition
+    // of an IF
+    pub fn evaluate(&self, line: usize, terms: &[Token]) -> Result<f32, ParseError> {
+        let joined = Joined::new(terms);
+
+        expr::parse(&joined.text)
+            .and_then(|expr| expr::evaluate(&expr, &self.variables))
+            .map_err(|e| joined.error(line, 0, e))
+    }
+
+    // Reads a single argument as a number or expression
+    pub fn number(&self, line: usize, token: &Token) -> Result<f32, ParseError> {
+        parse_number(line, token, &self.variables)
+    }
+
+    // #name = expression
+    fn assign(&mut self, line: usize, terms: &[Token]) -> Result<(), ParseError> {
+        let joined = Joined::new(terms);
+
+        let Some((name, value)) = joined.text.split_once('=') else {
+            return Err(ParseError::new(
+                line,
+                &terms[0],
+                ParseErrorKind::BadExpression("expected '=' after the variable".to_string()),
+            ));
+        };
+
+        let name = name.trim().trim_start_matches('#');
+
+        if !expr::is_identifier(name) {
+            return Err(ParseError::new(
+                line,
+                &terms[0],
+                ParseErrorKind::BadExpression("invalid variable name".to_string()),
+            ));
+        }
+
+        let offset = joined.text.split_once('=').unwrap().0.chars().count() + 1;
+        let value = expr::parse(value)
+            .and_then(|expr| expr::evaluate(&expr, &self.variables))
+            .map_err(|e| joined.error(line, offset, e))?;
+
+        self.variables.insert(name.to_lowercase(), value);
+
+        Ok(())
+    }
+}
+
+// Parses a single .rgcf line on its own, without variables. Blank and
+// comment-only lines hold no command.
+pub fn parse_line(line: usize, source: &str) -> Result<Option<Command>, ParseError> {
+    let source = strip_line_number(line, source)?;
+    let parsed = Parser::new(Dialect::Rgcf).parse_rgcf(line, &source)?;
+
+    Ok(parsed.map(|(command, _)| command))
+}
+
+// Parses every line, collecting all errors rather than stopping at the first.
+// The whole program is run to build the list, so a program that loops
+// forever never returns; use `Interpreter` to run those.
+pub fn parse_program(source: &str) -> Result<Vec<Command>, ParseReport> {
+    parse_program_as(source, Dialect::Rgcf)
+}
+
+pub fn parse_program_as(source: &str, dialect: Dialect) -> Result<Vec<Command>, ParseReport> {
+    let mut program: Vec<Command> = Vec::new();
+    let mut errors: Vec<ParseError> = Vec::new();
+
+    for result in Interpreter::from_source(source, dialect) {
+        match result {
+            Ok(command) => program.push(command),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(program)
+    } else {
+        Err(ParseReport { errors })
+    }
+}
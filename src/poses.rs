@@ -0,0 +1,61 @@
+// POSES
+// =====
+// POSE name X Y Z   Names a target, in the length units in effect
+// TG @name          Moves to a named target, absolute in either mode
+//
+// Poses shared between programs live in a pose file, an .rgcf program run
+// before the job whose poses are kept. The binary loads POSE_FILE from the
+// job's directory when there is one. A job can redefine a pose it loaded.
+
+use crate::error::{Origin, ParseError, ParseErrorKind, ParseReport};
+use crate::interpreter::Interpreter;
+use crate::parser::Dialect;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const POSE_FILE: &str = "poses.rgcf";
+
+// Targets in millimetres, by lowercase name
+pub type Poses = HashMap<String, [f32; 3]>;
+
+// Runs a pose file and keeps the poses it defines. Any commands it produces
+// are dropped, since the file is not sent to the arm.
+pub fn load(source: &str, path: &Path) -> Result<Poses, ParseReport> {
+    let mut program = Interpreter::from_source(source, Dialect::Rgcf).with_path(path);
+    let errors: Vec<ParseError> = program.by_ref().filter_map(Result::err).collect();
+
+    if errors.is_empty() {
+        Ok(program.poses().clone())
+    } else {
+        Err(ParseReport { errors })
+    }
+}
+
+// The pose file next to `job` and its poses, None when there is no pose file
+// other than the job itself
+pub fn load_beside(job: &Path) -> Option<(PathBuf, Result<Poses, ParseReport>)> {
+    let path = job.with_file_name(POSE_FILE);
+
+    if !path.is_file() || path == job {
+        return None;
+    }
+
+    let poses = match fs::read_to_string(&path) {
+        Ok(source) => load(&source, &path),
+        Err(e) => Err(ParseReport {
+            errors: vec![ParseError {
+                line: 1,
+                columns: 1..1,
+                token: String::new(),
+                kind: ParseErrorKind::ReadFailed(e.to_string()),
+                origin: Box::new(Origin {
+                    file: Some(path.clone()),
+                    included_from: Vec::new(),
+                }),
+            }],
+        }),
+    };
+
+    Some((path, poses))
+}
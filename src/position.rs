@@ -0,0 +1,59 @@
+// POSITIONING
+// ===========
+// The host follows the commanded position so relative targets are resolved
+// into absolute TG commands before anything is sent.
+//
+// AP             Absolute positioning, TG takes a target (default)
+// RP             Relative positioning, TG takes an offset
+// TR X Y Z       Offset from the current position, whatever the mode
+// TP X Y Z ...   Like TG, the orientation is always absolute
+//
+// G90/G91 switch the same mode in G-code files.
+//
+// RH and MN leave the position unknown until the next absolute target.
+
+use crate::command::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Position {
+    // Unknown until the first absolute target or homing
+    pub current: [Option<f32>; 3],
+    pub relative: bool,
+}
+impl Position {
+    // Follows the position through commands from either dialect
+    pub fn track(&mut self, command: &Command) {
+        match command {
+            Command::HM(x, y, z) | Command::TG(x, y, z) | Command::TP(x, y, z, ..) => {
+                self.current = [Some(*x), Some(*y), Some(*z)]
+            }
+            // A joint move leaves the tool somewhere the host does not follow
+            Command::RH | Command::MN(_) => self.current = [None; 3],
+            _ => {}
+        }
+    }
+
+    // TG to the `point` of `values`
+    pub fn resolve(&self, values: [Option<f32>; 3], relative: bool) -> Option<Command> {
+        let [x, y, z] = self.point(values, relative)?;
+        Some(Command::TG(x, y, z))
+    }
+
+    // Target for `values`, taken as offsets from the current position when
+    // `relative` is set. Axes left out keep their position. None when the
+    // position of an axis is needed but unknown.
+    pub fn point(&self, values: [Option<f32>; 3], relative: bool) -> Option<[f32; 3]> {
+        let mut target = [0.0; 3];
+
+        for (index, value) in values.into_iter().enumerate() {
+            target[index] = match (value, self.current[index], relative) {
+                (Some(value), _, false) => value,
+                (Some(value), Some(current), true) => current + value,
+                (None, Some(current), _) => current,
+                _ => return None,
+            };
+        }
+
+        Some(target)
+    }
+}
@@ -0,0 +1,97 @@
+// COMPUTER                     | ARDUINO MEGA → Robot arm actuators
+// Generate raw motor sequences | Execute motor sequences
+
+use serialport::SerialPort;
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+pub const BAUD_RATE: u32 = 115_200;
+pub const TIMEOUT: Duration = Duration::from_millis(5000);
+
+// Sent on connection, the Arduino answers with its own check string
+pub const CHECK_STRING: &str = "IN 0";
+
+// Sent by the Arduino once every queued move has finished
+pub const IDLE_STRING: &str = "IDLE";
+
+// Replies to numbered lines: accepted, or sent again from the given number
+pub const ACK_STRING: &str = "ok";
+pub const RESEND_PREFIXES: &[&str] = &["Resend:", "rs"];
+
+pub struct InitError {
+    pub message: String,
+    pub error: String,
+}
+impl InitError {
+    pub fn new(message: &str, error: &str) -> InitError {
+        InitError {
+            message: message.to_string(),
+            error: error.to_string(),
+        }
+    }
+}
+
+// Line based transport to the Arduino, implemented over anything that can be
+// written to and read from so programs can be run without hardware attached
+pub trait Link {
+    fn send(&mut self, line: &str) -> io::Result<()>;
+    fn receive(&mut self) -> io::Result<String>;
+}
+
+pub struct SerialLink {
+    port: Box<dyn SerialPort>,
+}
+impl SerialLink {
+    pub fn open(address: &str) -> serialport::Result<SerialLink> {
+        let port = serialport::new(address, BAUD_RATE)
+            .timeout(TIMEOUT)
+            .open()?;
+
+        Ok(SerialLink { port })
+    }
+
+    // init/write
+    pub fn send_check(&mut self) -> Result<(), InitError> {
+        self.port
+            .write_all(CHECK_STRING.as_bytes())
+            .map_err(|e| InitError::new("[init/write] Error sending check string.", &e.to_string()))
+    }
+
+    // init/read
+    pub fn read_check(&mut self) -> Result<String, InitError> {
+        let mut buf: Vec<u8> = vec![0; 100];
+
+        match self.port.read(&mut buf) {
+            Ok(n) => Ok(String::from_utf8_lossy(&buf[..n]).to_string()),
+            Err(e) => Err(InitError::new(
+                "[init/read] An error occurred trying to read from the device, or the connection timed out.",
+                &e.to_string(),
+            )),
+        }
+    }
+}
+impl Link for SerialLink {
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        self.port.write_all(line.as_bytes())?;
+        self.port.write_all(b"\n")?;
+        self.port.flush()
+    }
+
+    // Reads up to the next newline, without it
+    fn receive(&mut self) -> io::Result<String> {
+        let mut line: Vec<u8> = Vec::new();
+        let mut byte = [0u8; 1];
+
+        loop {
+            self.port.read_exact(&mut byte)?;
+
+            match byte[0] {
+                b'\n' => break,
+                b'\r' => {}
+                value => line.push(value),
+            }
+        }
+
+        Ok(String::from_utf8_lossy(&line).to_string())
+    }
+}
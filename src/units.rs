@@ -0,0 +1,133 @@
+// UNITS
+// =====
+// Lengths and angles in a program can be given in any of the units below.
+// Commands always leave the parser in millimetres and degrees.
+//
+// UN MM | UN IN            Cartesian targets (TG, TP, HM) and feed rates in
+//                          millimetres/inches
+// UN DEG | UN RAD | UN STEP  Manual axis moves (MN) in degrees/radians/steps,
+//                          tool orientation (TP) in degrees/radians
+//
+// G20/G21 select inches/millimetres in G-code files.
+
+use crate::command::{Axis, Command};
+
+pub const MM_PER_INCH: f32 = 25.4;
+
+// Motor steps (200 per turn at 16 microsteps) times each joint's gear ratio,
+// per degree of joint travel. Matches the Arctos v2.9 firmware settings.
+pub const STEPS_PER_DEGREE_X: f32 = 3200.0 * 13.5 / 360.0;
+pub const STEPS_PER_DEGREE_Y: f32 = 3200.0 * 150.0 / 360.0;
+pub const STEPS_PER_DEGREE_Z: f32 = 3200.0 * 150.0 / 360.0;
+pub const STEPS_PER_DEGREE_A: f32 = 3200.0 * 48.0 / 360.0;
+pub const STEPS_PER_DEGREE_B: f32 = 3200.0 * 67.82 / 360.0;
+pub const STEPS_PER_DEGREE_C: f32 = 3200.0 * 67.82 / 360.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LengthUnit {
+    #[default]
+    Millimetre,
+    Inch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AngleUnit {
+    #[default]
+    Degree,
+    Radian,
+    Step,
+}
+
+// A unit named on a `UN` line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Length(LengthUnit),
+    Angle(AngleUnit),
+}
+impl Unit {
+    pub fn from_name(name: &str) -> Option<Unit> {
+        match name.to_ascii_uppercase().as_str() {
+            "MM" => Some(Unit::Length(LengthUnit::Millimetre)),
+            "IN" => Some(Unit::Length(LengthUnit::Inch)),
+            "DEG" => Some(Unit::Angle(AngleUnit::Degree)),
+            "RAD" => Some(Unit::Angle(AngleUnit::Radian)),
+            "STEP" => Some(Unit::Angle(AngleUnit::Step)),
+            _ => None,
+        }
+    }
+}
+
+// Units in effect, carried from line to line like the dialect
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Units {
+    pub length: LengthUnit,
+    pub angle: AngleUnit,
+}
+impl Units {
+    pub fn set(&mut self, unit: Unit) {
+        match unit {
+            Unit::Length(length) => self.length = length,
+            Unit::Angle(angle) => self.angle = angle,
+        }
+    }
+
+    // To millimetres
+    pub fn length(&self, value: f32) -> f32 {
+        match self.length {
+            LengthUnit::Millimetre => value,
+            LengthUnit::Inch => value * MM_PER_INCH,
+        }
+    }
+
+    // To degrees, `steps_per_degree` being the joint's
+    pub fn angle(&self, value: f32, steps_per_degree: f32) -> f32 {
+        match self.angle {
+            AngleUnit::Degree => value,
+            AngleUnit::Radian => value.to_degrees(),
+            AngleUnit::Step => value / steps_per_degree,
+        }
+    }
+
+    // To degrees, an orientation belonging to no joint has no steps and
+    // stays in degrees under `UN STEP`
+    pub fn orientation(&self, value: f32) -> f32 {
+        match self.angle {
+            AngleUnit::Radian => value.to_degrees(),
+            AngleUnit::Degree | AngleUnit::Step => value,
+        }
+    }
+
+    fn axis(&self, axis: Axis) -> Axis {
+        match axis {
+            Axis::X(angle) => Axis::X(self.angle(angle, STEPS_PER_DEGREE_X)),
+            Axis::Y(angle) => Axis::Y(self.angle(angle, STEPS_PER_DEGREE_Y)),
+            Axis::Z(angle) => Axis::Z(self.angle(angle, STEPS_PER_DEGREE_Z)),
+            Axis::A(angle) => Axis::A(self.angle(angle, STEPS_PER_DEGREE_A)),
+            Axis::B(pitch, roll) => Axis::B(
+                self.angle(pitch, STEPS_PER_DEGREE_B),
+                self.angle(roll, STEPS_PER_DEGREE_B),
+            ),
+            Axis::C(angle) => Axis::C(self.angle(angle, STEPS_PER_DEGREE_C)),
+        }
+    }
+
+    // Rewrites a command parsed in these units into millimetres and degrees
+    pub fn canonical(&self, command: Command) -> Command {
+        match command {
+            Command::HM(x, y, z) => Command::HM(self.length(x), self.length(y), self.length(z)),
+            Command::TG(x, y, z) => Command::TG(self.length(x), self.length(y), self.length(z)),
+            Command::TP(x, y, z, roll, pitch, yaw) => Command::TP(
+                self.length(x),
+                self.length(y),
+                self.length(z),
+                self.orientation(roll),
+                self.orientation(pitch),
+                self.orientation(yaw),
+            ),
+            Command::MN(axis) => Command::MN(self.axis(axis)),
+            Command::FR(feed) => Command::FR(self.length(feed)),
+            Command::AC(accel) => Command::AC(self.length(accel)),
+            _ => command,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use roboarm_gcode::ast::{self, BlockKind, Line, Node, Span};
+use roboarm_gcode::interpreter::Interpreter;
+use roboarm_gcode::parser::Dialect;
+use std::ops::Range;
+
+#[test]
+fn lines_keep_terms_comments_and_spans() {
+    let line = Line::parse(3, "  N10 TG 1 [#x + 2](above)  3*71 ; pick", Dialect::Rgcf);
+    let terms: Vec<(&str, Range<usize>)> = line
+        .terms()
+        .map(|term| (term.text.as_str(), term.span.columns.clone()))
+        .collect();
+
+    assert_eq!(line.indent(), "  ");
+    assert_eq!(line.line_number.as_ref().unwrap().text, "N10");
+    assert_eq!(line.checksum.as_ref().unwrap().span.columns, 30..33);
+    assert_eq!(
+        terms,
+        vec![
+            ("TG", 7..9),
+            ("1", 10..11),
+            ("[#x + 2]", 12..20),
+            ("3", 29..30)
+        ]
+    );
+
+    let comments: Vec<(&str, bool)> = line
+        .comments()
+        .map(|comment| (comment.text.as_str(), comment.attached))
+        .collect();
+    assert_eq!(comments, vec![("(above)", true), ("; pick", false)]);
+    assert_eq!(line.span().columns, 3..40);
+    assert!(Line::parse(1, "TG 1 (open", Dialect::Rgcf).unterminated);
+}
+
+#[test]
+fn blocks_nest_and_commands_map_back() {
+    let source = "SUB pick(x)\n  TG #x 0 0\nEND\nIF 1\n  CALL pick 5\nELSE\n  NO\nEND\nREPEAT 2";
+    let program = ast::parse(source, Dialect::Rgcf);
+
+    assert_eq!(program.nodes.len(), 3);
+    assert_eq!(program.lines().len(), 9);
+
+    let Node::Block(block) = &program.nodes[1] else {
+        panic!("expected a block");
+    };
+    assert_eq!(block.kind, BlockKind::If);
+    assert_eq!(block.otherwise.as_ref().unwrap().0.number, 6);
+    assert_eq!(
+        program.nodes[1].span(),
+        Span {
+            line: 4,
+            columns: 1..4
+        }
+    );
+
+    let Node::Block(unclosed) = &program.nodes[2] else {
+        panic!("expected a block");
+    };
+    assert_eq!(unclosed.end, None);
+
+    let mut interpreter = Interpreter::from_source(source, Dialect::Rgcf);
+    interpreter.next().unwrap().unwrap();
+    let (line, _) = interpreter.location().unwrap();
+    let statement = program.line(line).unwrap();
+
+    assert_eq!(statement.keyword(), Some("TG"));
+    assert_eq!(statement.term_at(6).unwrap().text, "#x");
+}
+
+#[test]
+fn non_ascii_lines_parse() {
+    let program = ast::parse("ö\nTG 1 2 3 ; über", Dialect::Rgcf);
+    let lines = program.lines();
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].keyword(), Some("ö"));
+    assert_eq!(lines[1].trailing.as_ref().unwrap().text, "; über");
+
+    let line = Line::parse(1, "°C 1", Dialect::Rgcf);
+    let terms: Vec<(&str, Range<usize>)> = line
+        .terms()
+        .map(|term| (term.text.as_str(), term.span.columns.clone()))
+        .collect();
+    assert_eq!(terms, vec![("°C", 1..3), ("1", 4..5)]);
+    assert_eq!(line.line_number, None);
+}
@@ -0,0 +1,173 @@
+use roboarm_gcode::binary::{self, CompileError, DecodeError};
+use roboarm_gcode::interpreter::Interpreter;
+use roboarm_gcode::kinematics::Arm;
+use roboarm_gcode::lint::{Limits, RobotProfile};
+use roboarm_gcode::{Axis, Command, Dialect, parse_program};
+
+const PROGRAM: &str = "HM 0 0 0\nREPEAT 2\n  TG 150 20.5 -3\nEND\nCL OPEN=30\nMN B 10 -5\nDW 250\nWI\nFR 1200\nTP 100 0 50 180 0 30\nRH";
+
+const KEY: &[u8] = b"shared by the workstation and the shop floor";
+
+fn compiled() -> Vec<u8> {
+    let program = Interpreter::from_source(PROGRAM, Dialect::Rgcf);
+    binary::compile(
+        program,
+        &RobotProfile::default(),
+        &Arm::default(),
+        KEY,
+        1000,
+    )
+    .unwrap()
+}
+
+#[test]
+fn compiled_programs_decode_to_the_same_commands() {
+    let bytes = compiled();
+    let commands = binary::decode(&bytes, &RobotProfile::default(), &Arm::default(), KEY).unwrap();
+
+    assert_eq!(&bytes[..4], b"RGCB");
+    assert_eq!(commands, parse_program(PROGRAM).unwrap());
+    assert_eq!(commands[4], Command::MN(Axis::B(10.0, -5.0)));
+
+    let text = binary::decompile(&bytes, &RobotProfile::default(), &Arm::default(), KEY).unwrap();
+    assert_eq!(parse_program(&text).unwrap(), commands);
+    assert!(text.starts_with("HM 0 0 0\nTG 150 20.5 -3\n"));
+}
+
+#[test]
+fn changed_files_are_rejected() {
+    let profile = RobotProfile::default();
+    let arm = Arm::default();
+    let bytes = compiled();
+
+    let mut tampered = bytes.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert_eq!(
+        binary::decode(&tampered, &profile, &arm, KEY),
+        Err(DecodeError::BadSignature)
+    );
+
+    assert_eq!(
+        binary::decode(&bytes[..bytes.len() - 2], &profile, &arm, KEY),
+        Err(DecodeError::BadSignature)
+    );
+    assert_eq!(
+        binary::decode(&bytes, &profile, &arm, b"another key"),
+        Err(DecodeError::BadSignature)
+    );
+    assert_eq!(
+        binary::decode(b"TG 1 2 3", &profile, &arm, KEY),
+        Err(DecodeError::BadMagic)
+    );
+
+    let other = RobotProfile {
+        y: Limits::new(-45.0, 45.0),
+        ..profile
+    };
+    assert_eq!(
+        binary::decode(&bytes, &other, &arm, KEY),
+        Err(DecodeError::ProfileMismatch)
+    );
+
+    // Same limits, a longer forearm
+    let mut longer = arm;
+    longer.links[3].d += 10.0;
+    assert_eq!(
+        binary::decode(&bytes, &profile, &longer, KEY),
+        Err(DecodeError::ProfileMismatch)
+    );
+}
+
+#[test]
+fn only_finished_valid_programs_compile() {
+    let profile = RobotProfile::default();
+
+    let forever = Interpreter::from_source("WHILE 1\n  TG 1 2 3\nEND", Dialect::Rgcf);
+    assert_eq!(
+        binary::compile(forever, &profile, &Arm::default(), KEY, 100),
+        Err(CompileError::Unbounded(100))
+    );
+
+    // Exactly `limit` commands is a program that ended
+    let short = Interpreter::from_source("HM 0 0 0\nTG 150 0 0\nRH", Dialect::Rgcf);
+    assert!(binary::compile(short, &profile, &Arm::default(), KEY, 3).is_ok());
+
+    let broken = Interpreter::from_source("TG 1 2", Dialect::Rgcf);
+    assert!(matches!(
+        binary::compile(broken, &profile, &Arm::default(), KEY, 100),
+        Err(CompileError::Parse(_))
+    ));
+}
+
+#[test]
+fn programs_outside_the_arm_do_not_compile() {
+    let profile = RobotProfile::default();
+
+    let wide = Interpreter::from_source("HM 0 0 0\nCL OPEN=90", Dialect::Rgcf);
+    match binary::compile(wide, &profile, &Arm::default(), KEY, 100) {
+        Err(CompileError::Limits(diagnostics)) => {
+            assert_eq!(diagnostics.len(), 1);
+            assert_eq!(diagnostics[0].line, 2);
+        }
+        other => panic!("expected a limit error, got {:?}", other),
+    }
+
+    let far = Interpreter::from_source("HM 0 0 0\nTG 1000 0 0", Dialect::Rgcf);
+    match binary::compile(far, &profile, &Arm::default(), KEY, 100) {
+        Err(CompileError::Unreachable(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].line, 2);
+        }
+        other => panic!("expected an unreachable target, got {:?}", other),
+    }
+
+    // Style warnings do not stop a compile
+    let noisy = Interpreter::from_source("NO\nHM 0 0 0", Dialect::Rgcf);
+    assert!(binary::compile(noisy, &profile, &Arm::default(), KEY, 100).is_ok());
+}
+
+#[test]
+fn signed_files_are_checked_again_when_decoded() {
+    let profile = RobotProfile::default();
+    let arm = Arm::default();
+
+    // Signed with the key, but never compiled
+    let wide = [
+        Command::HM(0.0, 0.0, 0.0),
+        Command::CL(90.0, 2.0, 10.0, 5.0, 0.0),
+    ];
+    match binary::decode(
+        &binary::encode(&wide, &profile, &arm, KEY),
+        &profile,
+        &arm,
+        KEY,
+    ) {
+        Err(DecodeError::Limits(diagnostics)) => assert_eq!(diagnostics[0].line, 2),
+        other => panic!("expected a limit error, got {:?}", other),
+    }
+
+    let far = [Command::HM(0.0, 0.0, 0.0), Command::TG(1000.0, 0.0, 0.0)];
+    match binary::decode(
+        &binary::encode(&far, &profile, &arm, KEY),
+        &profile,
+        &arm,
+        KEY,
+    ) {
+        Err(DecodeError::Unreachable(errors)) => assert_eq!(errors[0].line, 2),
+        other => panic!("expected an unreachable target, got {:?}", other),
+    }
+
+    let endless = [Command::HM(0.0, 0.0, 0.0), Command::DW(f32::NAN)];
+    assert_eq!(
+        binary::decode(
+            &binary::encode(&endless, &profile, &arm, KEY),
+            &profile,
+            &arm,
+            KEY
+        ),
+        Err(DecodeError::NotFinite {
+            offset: binary::HEADER_SIZE + 13 + 1
+        })
+    );
+}
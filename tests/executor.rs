@@ -0,0 +1,109 @@
+use roboarm_gcode::executor::Executor;
+use roboarm_gcode::serial::Link;
+use roboarm_gcode::{Command, parse_program};
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+
+// Records what is sent and plays back canned replies
+#[derive(Default)]
+struct MockLink {
+    sent: Vec<String>,
+    replies: VecDeque<io::Result<String>>,
+}
+impl Link for MockLink {
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        self.sent.push(line.to_string());
+        Ok(())
+    }
+
+    fn receive(&mut self) -> io::Result<String> {
+        self.replies
+            .pop_front()
+            .unwrap_or_else(|| Err(io::Error::from(ErrorKind::UnexpectedEof)))
+    }
+}
+
+#[test]
+fn wait_blocks_until_idle() {
+    let program = parse_program("CL OPEN=0\nDW 2000\nWI\nTG 1 2 3").unwrap();
+    let mut executor = Executor::new(MockLink {
+        replies: VecDeque::from([
+            Ok("BUSY".to_string()),
+            Err(io::Error::from(ErrorKind::TimedOut)),
+            Ok("IDLE".to_string()),
+        ]),
+        ..MockLink::default()
+    });
+
+    executor.run(&program).unwrap();
+
+    assert_eq!(
+        executor.link().sent,
+        vec!["CL 0 2 10 5 0", "DW 2000", "WI", "TG 1 2 3"]
+    );
+    assert!(executor.link().replies.is_empty());
+}
+
+#[test]
+fn wait_fails_when_the_link_does() {
+    let mut executor = Executor::new(MockLink::default());
+
+    assert!(executor.execute(&Command::WI).is_err());
+    assert!(executor.execute(&Command::DW(10.0)).is_ok());
+}
+
+#[test]
+fn numbered_lines_are_sent_again_on_request() {
+    let program = parse_program("HM 0 0 0\nTG 1 2 3").unwrap();
+    let replies = ["ok", "Error:checksum mismatch", "Resend: 2", "ok", "ok"];
+    let mut executor = Executor::new(MockLink {
+        replies: replies.iter().map(|reply| Ok(reply.to_string())).collect(),
+        ..MockLink::default()
+    })
+    .with_line_numbers();
+
+    executor.run(&program).unwrap();
+
+    assert_eq!(
+        executor.link().sent,
+        vec!["N1 HM 0 0 0*74", "N2 TG 1 2 3*95", "N2 TG 1 2 3*95"]
+    );
+    // The `ok` after the resend request did not stand in for the resent line's
+    assert!(executor.link().replies.is_empty());
+}
+
+#[test]
+fn acknowledgements_are_waited_for_through_timeouts() {
+    // A long move fills the queue and holds back the `ok`
+    let mut executor = Executor::new(MockLink {
+        replies: VecDeque::from([
+            Err(io::Error::from(ErrorKind::TimedOut)),
+            Err(io::Error::from(ErrorKind::TimedOut)),
+            Ok("ok".to_string()),
+        ]),
+        ..MockLink::default()
+    })
+    .with_line_numbers();
+
+    executor.execute(&Command::TG(1.0, 2.0, 3.0)).unwrap();
+    assert!(executor.link().replies.is_empty());
+
+    // Any other error still stops the job
+    assert_eq!(
+        executor.execute(&Command::RH).unwrap_err().kind(),
+        ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn resend_of_an_unknown_line_fails() {
+    let mut executor = Executor::new(MockLink {
+        replies: VecDeque::from([Ok("rs 7".to_string())]),
+        ..MockLink::default()
+    })
+    .with_line_numbers();
+
+    let error = executor.execute(&Command::RH).unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
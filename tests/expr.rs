@@ -0,0 +1,41 @@
+use roboarm_gcode::{Command, ParseErrorKind, parse_program};
+
+#[test]
+fn variables_and_expressions() {
+    let program = parse_program(
+        "#safe_z = 120\n#x=10\n#y = #x * 2\nTG #x+10 #y [#safe_z*0.5]\nTG X=[sqrt(9) + sin(90)] Y=#y-1 Z=2^3",
+    )
+    .unwrap();
+
+    assert_eq!(
+        program,
+        vec![Command::TG(20.0, 20.0, 60.0), Command::TG(4.0, 19.0, 8.0)]
+    );
+}
+
+#[test]
+fn numbers_with_exponents() {
+    let program = parse_program("#e = 2\nTG [1e3 + 1] [2.5E-1 * 4] [1e+1-#e]").unwrap();
+
+    assert_eq!(program, vec![Command::TG(1001.0, 1.0, 8.0)]);
+}
+
+#[test]
+fn expression_errors_point_into_the_line() {
+    let report =
+        parse_program("TG #nope 1 2\nTG 1 [2 + ] 3\n#z = 1 / 0\nTG [foo(1)] 2 3").unwrap_err();
+    let errors: Vec<(usize, usize, ParseErrorKind)> = report
+        .errors
+        .into_iter()
+        .map(|e| (e.line, e.columns.start, e.kind))
+        .collect();
+
+    assert_eq!(errors[0], (1, 4, ParseErrorKind::UndefinedVariable));
+    assert_eq!(errors[1].0, 2);
+    assert_eq!(errors[1].1, 11);
+    assert!(matches!(
+        errors[2],
+        (3, 6, ParseErrorKind::BadExpression(_))
+    ));
+    assert_eq!(errors[3], (4, 5, ParseErrorKind::UnknownFunction));
+}
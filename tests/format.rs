@@ -0,0 +1,91 @@
+use roboarm_gcode::format::format_program;
+use roboarm_gcode::parser::parse_line;
+use roboarm_gcode::{Axis, Command, Dialect, parse_program};
+
+const MESSY: &str = "; setup
+#safe_z   =  150.0
+POSE home 0 0 +200
+SUB pick(x, y)   ; approach
+TG   #x  #y   #safe_z
+      CL  OPEN=0.00  FORCE=2.50 (grip)
+IF [#x > 10]
+DW 0500
+ELSE
+TG @home F=100.0
+END
+END
+
+   REPEAT 2
+CALL pick 100 050.0
+ END
+N10 TG 1 2 3*   65
+%gcode
+G1   X10
+";
+
+const CANONICAL: &str = "; setup
+#safe_z = 150
+POSE home 0 0 200
+SUB pick(x, y) ; approach
+  TG #x #y #safe_z
+  CL OPEN=0 FORCE=2.5 (grip)
+  IF [#x > 10]
+    DW 500
+  ELSE
+    TG @home F=100
+  END
+END
+
+REPEAT 2
+  CALL pick 100 50
+END
+N10 TG 1 2 3*   65
+%gcode
+G1   X10
+";
+
+#[test]
+fn commands_print_as_rgcf() {
+    let commands = [
+        Command::NO,
+        Command::HM(0.0, -0.5, 1e-3),
+        Command::TG(120.25, 40.0, 150.0),
+        Command::CL(30.0, 2.0, 10.0, 5.0, 0.0),
+        Command::DW(500.0),
+        Command::MN(Axis::B(12.5, -90.0)),
+        Command::MN(Axis::C(0.1)),
+        Command::FR(1200.0),
+        Command::WI,
+    ];
+
+    assert_eq!(commands[2].to_string(), "TG 120.25 40 150");
+    assert_eq!(commands[5].to_string(), "MN B 12.5 -90");
+
+    for command in commands {
+        assert_eq!(parse_line(1, &command.to_string()).unwrap(), Some(command));
+    }
+}
+
+#[test]
+fn formats_into_canonical_form() {
+    assert_eq!(format_program(MESSY, Dialect::Rgcf), CANONICAL);
+    assert_eq!(format_program(CANONICAL, Dialect::Rgcf), CANONICAL);
+}
+
+#[test]
+fn formatting_keeps_the_program() {
+    let source = MESSY.replace("N10 TG 1 2 3*   65\n", "CALL pick 1 2\n");
+    let formatted = format_program(&source, Dialect::Rgcf);
+
+    assert!(parse_program(&source).is_ok());
+    assert_eq!(parse_program(&formatted), parse_program(&source));
+}
+
+#[test]
+fn non_ascii_lines_are_kept() {
+    assert_eq!(format_program("°C 1", Dialect::Rgcf), "°C 1\n");
+    assert_eq!(
+        format_program("é 1 2 3\nTG 1 2 3 ; über\n", Dialect::Rgcf),
+        "é 1 2 3\nTG 1 2 3 ; über\n"
+    );
+}
@@ -0,0 +1,90 @@
+use roboarm_gcode::{Command, Dialect, ParseErrorKind, parse_program, parse_program_as};
+
+fn gcode(source: &str) -> Vec<Command> {
+    parse_program_as(source, Dialect::GCode).unwrap()
+}
+
+#[test]
+fn moves_keep_left_out_axes() {
+    assert_eq!(
+        gcode("G1 X10 Y20 Z5 F1200\nG0 Z8\nG91\nG1X1"),
+        vec![
+            Command::FR(1200.0),
+            Command::TG(10.0, 20.0, 5.0),
+            Command::TG(10.0, 20.0, 8.0),
+            Command::TG(11.0, 20.0, 8.0),
+        ]
+    );
+}
+
+#[test]
+fn dwell_home_and_claw() {
+    let program = gcode("G4 P500\nG4 S1.5\nG28\nM3 S4\nM5\nM112");
+
+    assert_eq!(program[0], Command::DW(500.0));
+    assert_eq!(program[1], Command::DW(1500.0));
+    assert_eq!(program[2], Command::RH);
+    assert!(matches!(program[3], Command::CL(open, 4.0, ..) if open == 0.0));
+    assert!(matches!(program[4], Command::CL(open, ..) if open > 0.0));
+    assert_eq!(program[5], Command::FS);
+}
+
+#[test]
+fn cam_preambles_are_ignored() {
+    assert_eq!(
+        gcode("G21 G90 G17\nG94 G54\nG40 G49 G80\nG18\nG59\nG1 X1 Y2 Z3"),
+        vec![Command::TG(1.0, 2.0, 3.0)]
+    );
+
+    let report = parse_program_as("G54.1 P1\nG41", Dialect::GCode).unwrap_err();
+    assert!(
+        report
+            .errors
+            .iter()
+            .all(|e| e.kind == ParseErrorKind::UnsupportedCode)
+    );
+    assert_eq!(report.errors.len(), 2);
+}
+
+#[test]
+fn unknown_position_and_codes_are_reported() {
+    let report = parse_program_as("G1 X1\nG28\nT1", Dialect::GCode).unwrap_err();
+    let kinds: Vec<ParseErrorKind> = report.errors.into_iter().map(|e| e.kind).collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            ParseErrorKind::UnknownPosition,
+            ParseErrorKind::UnsupportedCode
+        ]
+    );
+}
+
+#[test]
+fn dialect_directive_switches_dialect() {
+    assert_eq!(
+        parse_program("TG 1 2 3\n%gcode\nG1 X4\n%rgcf\nRH").unwrap(),
+        vec![
+            Command::TG(1.0, 2.0, 3.0),
+            Command::TG(4.0, 2.0, 3.0),
+            Command::RH
+        ]
+    );
+}
+
+#[test]
+fn program_end_stops_the_program() {
+    assert_eq!(
+        gcode("G1 X1 Y2 Z3\nM30\nG1 X5"),
+        vec![Command::TG(1.0, 2.0, 3.0)]
+    );
+    assert_eq!(
+        gcode("G1 X1 Y2 Z3\nM2 G1 X5\nG1 X6"),
+        vec![Command::TG(1.0, 2.0, 3.0)]
+    );
+    // Nothing after the end is run, whatever the dialect
+    assert_eq!(
+        gcode("G1 X1 Y2 Z3\nM30\n%rgcf\nTG 1 1 1"),
+        vec![Command::TG(1.0, 2.0, 3.0)]
+    );
+}
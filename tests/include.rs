@@ -0,0 +1,126 @@
+use roboarm_gcode::interpreter::Interpreter;
+use roboarm_gcode::parser::Dialect;
+use roboarm_gcode::{Command, ParseError, ParseErrorKind};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Writes `files` into a fresh directory and returns its path
+fn workspace(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("roboarm_include_{}", name));
+    let _ = fs::remove_dir_all(&directory);
+
+    for (path, contents) in files {
+        let path = directory.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    directory
+}
+
+fn run(directory: &Path, main: &str) -> Vec<Result<Command, ParseError>> {
+    let path = directory.join(main);
+    let source = fs::read_to_string(&path).unwrap();
+
+    Interpreter::from_source(&source, Dialect::Rgcf)
+        .with_path(&path)
+        .collect()
+}
+
+#[test]
+fn includes_relative_to_the_including_file() {
+    let directory = workspace(
+        "relative",
+        &[
+            ("job.rgcf", "INCLUDE \"common/setup.rgcf\"\nCALL pick 10 20"),
+            ("common/setup.rgcf", "HM 0 0 0\nINCLUDE poses.rgcf"),
+            ("common/poses.rgcf", "SUB pick(x, y)\n  TG #x #y 100\nEND"),
+        ],
+    );
+
+    let commands: Vec<Command> = run(&directory, "job.rgcf")
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(
+        commands,
+        vec![Command::HM(0.0, 0.0, 0.0), Command::TG(10.0, 20.0, 100.0)]
+    );
+}
+
+#[test]
+fn included_modes_end_with_the_file() {
+    let directory = workspace(
+        "modes",
+        &[
+            (
+                "job.rgcf",
+                "HM 0 0 0\nINCLUDE approach.rgcf\nTG 1 2 3\nTG 1 1 1",
+            ),
+            ("approach.rgcf", "UN IN\nRP\nTG 1 0 0"),
+        ],
+    );
+
+    let commands: Vec<Command> = run(&directory, "job.rgcf")
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(
+        commands,
+        vec![
+            Command::HM(0.0, 0.0, 0.0),
+            Command::TG(25.4, 0.0, 0.0),
+            Command::TG(1.0, 2.0, 3.0),
+            Command::TG(1.0, 1.0, 1.0),
+        ]
+    );
+}
+
+#[test]
+fn errors_report_the_include_chain() {
+    let directory = workspace(
+        "chain",
+        &[
+            ("job.rgcf", "HM 0 0 0\nINCLUDE \"lib/a.rgcf\""),
+            ("lib/a.rgcf", "TG 1 2 3\nTG 1 2"),
+        ],
+    );
+
+    let error = run(&directory, "job.rgcf").pop().unwrap().unwrap_err();
+
+    assert_eq!(error.line, 2);
+    assert_eq!(error.origin.file, Some(directory.join("lib/a.rgcf")));
+    assert_eq!(error.origin.included_from.len(), 1);
+    assert_eq!(error.origin.included_from[0].line, 2);
+    assert!(error.to_string().ends_with(&format!(
+        "included from {} line 2",
+        directory.join("job.rgcf").display()
+    )));
+}
+
+#[test]
+fn include_errors() {
+    let directory = workspace(
+        "errors",
+        &[
+            (
+                "job.rgcf",
+                "INCLUDE a.rgcf\nINCLUDE missing.rgcf\nREPEAT 2\nINCLUDE a.rgcf\nEND",
+            ),
+            ("a.rgcf", "INCLUDE b.rgcf"),
+            ("b.rgcf", "INCLUDE job.rgcf"),
+        ],
+    );
+
+    let kinds: Vec<ParseErrorKind> = run(&directory, "job.rgcf")
+        .into_iter()
+        .map(|result| result.unwrap_err().kind)
+        .collect();
+
+    assert!(matches!(kinds[0], ParseErrorKind::IncludeCycle));
+    assert!(matches!(kinds[1], ParseErrorKind::IncludeFailed(_)));
+    assert_eq!(kinds[2], ParseErrorKind::IncludeInBlock);
+    assert_eq!(kinds.len(), 3);
+}
@@ -0,0 +1,101 @@
+use roboarm_gcode::{Axis, Command, Dialect, Interpreter, ParseErrorKind, parse_program};
+
+#[test]
+fn repeat_and_if_else() {
+    let program = parse_program(
+        "#i = 0
+REPEAT 3
+  #i = #i + 1
+  IF [#i == 2]
+    TG #i 0 0
+  ELSE
+    TG 0 #i 0
+  END
+END",
+    )
+    .unwrap();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::TG(0.0, 1.0, 0.0),
+            Command::TG(2.0, 0.0, 0.0),
+            Command::TG(0.0, 3.0, 0.0),
+        ]
+    );
+}
+
+#[test]
+fn while_loop() {
+    let program = parse_program("#i = 2\nWHILE #i >= 0\n  MN X #i\n  #i = #i - 1\nEND").unwrap();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::MN(Axis::X(2.0)),
+            Command::MN(Axis::X(1.0)),
+            Command::MN(Axis::X(0.0)),
+        ]
+    );
+}
+
+#[test]
+fn endless_loops_are_produced_lazily() {
+    let interpreter = Interpreter::from_source("WHILE 1\n  RH\nEND", Dialect::Rgcf);
+    let commands: Vec<Command> = interpreter.take(1000).map(Result::unwrap).collect();
+
+    assert_eq!(commands.len(), 1000);
+}
+
+#[test]
+fn conditions_read_runtime_inputs() {
+    let mut interpreter =
+        Interpreter::from_source("#part = 1\nWHILE #part\n  TG 1 2 3\nEND\nRH", Dialect::Rgcf);
+
+    assert_eq!(interpreter.next(), Some(Ok(Command::TG(1.0, 2.0, 3.0))));
+    assert_eq!(interpreter.next(), Some(Ok(Command::TG(1.0, 2.0, 3.0))));
+
+    interpreter.set_variable("part", 0.0);
+
+    assert_eq!(interpreter.next(), Some(Ok(Command::RH)));
+    assert_eq!(interpreter.next(), None);
+}
+
+#[test]
+fn block_errors() {
+    let kinds: Vec<ParseErrorKind> =
+        parse_program("REPEAT 2\n  TG 1\nEND\nELSE\nIF 1\n  SUB x\n  END\nEND\nWHILE 1")
+            .unwrap_err()
+            .errors
+            .into_iter()
+            .map(|e| e.kind)
+            .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            ParseErrorKind::WrongArity {
+                expected: 3,
+                found: 1
+            },
+            ParseErrorKind::UnexpectedEnd,
+            ParseErrorKind::NestedDefinition,
+            ParseErrorKind::UnterminatedBlock,
+        ]
+    );
+}
+
+#[test]
+fn only_loops_can_run_away() {
+    let mut program = Interpreter::from_source("RH\nWHILE 1\n  #x = 1\nEND", Dialect::Rgcf);
+    assert_eq!(program.next(), Some(Ok(Command::RH)));
+
+    let error = program.next().unwrap().unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::RunawayLoop);
+    assert_eq!(error.line, 2);
+
+    // However many top-level lines produce nothing, they are not a loop
+    let source = format!("{}TG 1 2 3", "; nothing\n".repeat(1_000_001));
+    let mut program = Interpreter::from_source(&source, Dialect::Rgcf);
+    assert_eq!(program.next(), Some(Ok(Command::TG(1.0, 2.0, 3.0))));
+}
@@ -0,0 +1,252 @@
+use roboarm_gcode::interpreter::Interpreter;
+use roboarm_gcode::kinematics::{self, Arm, Joints, Unreachable};
+use roboarm_gcode::lint::{Limits, RobotProfile};
+use roboarm_gcode::parser::Dialect;
+use roboarm_gcode::{Axis, Command};
+
+fn assert_near(actual: [f32; 3], expected: [f32; 3]) {
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn tool_pose_follows_the_joints() {
+    let arm = Arm::default();
+
+    // Upper arm up, forearm and tool forward
+    let home = arm.forward(&[0.0; 6]);
+    assert_near(home.position, [355.672, 0.0, 568.075]);
+    assert_near(home.approach(), [1.0, 0.0, 0.0]);
+
+    // The base turns the whole arm about z
+    let turned = arm.forward(&[90.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    assert_near(turned.position, [0.0, 355.672, 568.075]);
+
+    // Wrist pitched down, the gripper is vertical
+    let down = arm.forward(&[0.0, 0.0, 0.0, 0.0, 90.0, 0.0]);
+    assert_near(down.position, [280.927, 0.0, 493.33]);
+    assert_near(down.approach(), [0.0, 0.0, -1.0]);
+
+    // Rolling the forearm and the tool leaves the wrist centre in place
+    let rolled = arm.forward(&[0.0, 0.0, 0.0, 45.0, 90.0, 30.0]);
+    let wrist = arm.frames(&[0.0, 0.0, 0.0, 45.0, 90.0, 30.0])[4];
+    assert_near(
+        [wrist[0][3] as f32, wrist[1][3] as f32, wrist[2][3] as f32],
+        [280.927, 0.0, 568.075],
+    );
+    assert!((rolled.position[2] - 568.075).abs() < 74.746);
+}
+
+#[test]
+fn orientation_is_roll_pitch_yaw() {
+    let arm = Arm::default();
+    let assert_angles = |joints: Joints, expected: [f32; 3]| {
+        let actual = arm.forward(&joints).orientation();
+
+        for (actual, expected) in actual.iter().zip(expected) {
+            let difference = (actual - expected + 540.0).rem_euclid(360.0) - 180.0;
+            assert!(
+                difference.abs() < 1e-3,
+                "{:?} is not {:?}",
+                actual,
+                expected
+            );
+        }
+    };
+
+    assert_angles([0.0, -90.0, 0.0, 0.0, 0.0, 0.0], [0.0, 0.0, 180.0]);
+    assert_angles([0.0, 0.0, 0.0, 0.0, 90.0, 0.0], [180.0, 0.0, 0.0]);
+    assert_angles([30.0, 0.0, 0.0, 0.0, 90.0, 0.0], [180.0, 0.0, 30.0]);
+}
+
+#[test]
+fn manual_moves_set_joints() {
+    let mut joints: Joints = [0.0; 6];
+
+    for command in [
+        Command::MN(Axis::X(10.0)),
+        Command::MN(Axis::B(20.0, 30.0)),
+        Command::MN(Axis::C(40.0)),
+        Command::TG(1.0, 2.0, 3.0),
+    ] {
+        kinematics::track(&mut joints, &command);
+    }
+
+    assert_eq!(joints, [10.0, 0.0, 0.0, 0.0, 20.0, 40.0]);
+
+    kinematics::track(&mut joints, &Command::RH);
+    assert_eq!(joints, [0.0; 6]);
+}
+
+#[test]
+fn inverse_finds_the_joints_of_a_target() {
+    let arm = Arm::default();
+    let limits = kinematics::joint_limits(&RobotProfile::default());
+    let current: Joints = [10.0, 20.0, 30.0, 0.0, 40.0, 0.0];
+
+    for target in [
+        [300.0, 0.0, 300.0],
+        [200.0, 100.0, 100.0],
+        [100.0, -200.0, 450.0],
+    ] {
+        let solutions = arm.inverse(target, &current, &limits).unwrap();
+
+        for joints in &solutions {
+            assert_near(arm.forward(joints).position, target);
+        }
+
+        // Nearest configuration first
+        assert!(solutions.windows(2).all(|pair| {
+            kinematics::distance(&pair[0], &current) <= kinematics::distance(&pair[1], &current)
+        }));
+    }
+
+    // Already there, nothing moves
+    let here = arm.forward(&current).position;
+    let solutions = arm.inverse(here, &current, &limits).unwrap();
+    assert!(kinematics::distance(&solutions[0], &current) < 0.01);
+}
+
+#[test]
+fn inverse_reports_unreachable_targets() {
+    let arm = Arm::default();
+    let limits = kinematics::joint_limits(&RobotProfile::default());
+
+    assert_eq!(
+        arm.inverse([0.0, 0.0, 900.0], &[0.0; 6], &limits),
+        Err(Unreachable::OutOfReach)
+    );
+    assert_eq!(
+        arm.inverse([-300.0, 0.0, 100.0], &[0.0; 6], &limits),
+        Err(Unreachable::JointLimits)
+    );
+}
+
+#[test]
+fn the_tool_roll_is_limited_by_the_profile_roll() {
+    let arm = Arm::default();
+    let profile = RobotProfile {
+        roll: Limits::new(-10.0, 10.0),
+        ..RobotProfile::default()
+    };
+    let limits = kinematics::joint_limits(&profile);
+
+    assert_eq!(limits[5], profile.roll);
+    assert!(
+        arm.inverse_pose([300.0, 0.0, 100.0], [180.0, 0.0, 0.0], &[0.0; 6], &limits)
+            .is_ok()
+    );
+    assert_eq!(
+        arm.inverse_pose([300.0, 0.0, 100.0], [180.0, 0.0, 90.0], &[0.0; 6], &limits),
+        Err(Unreachable::JointLimits)
+    );
+}
+
+#[test]
+fn check_reach_follows_the_program() {
+    let source = "TG 300 0 300\nMN X 45\nTG 0 0 900\nHM 0 0 0\nTG 200 100 100\n";
+    let program = Interpreter::from_source(source, Dialect::Rgcf);
+    let errors = kinematics::check_reach(program, &Arm::default(), &RobotProfile::default(), 100)
+        .unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line, 3);
+    assert_eq!(errors[0].reason, Unreachable::OutOfReach);
+    assert_eq!(
+        errors[0].to_json(),
+        "{\"severity\":\"error\",\"file\":null,\"line\":3,\"code\":\"unreachable\",\"message\":\"target is out of the arm's reach\",\"command\":\"TG 0 0 900\"}"
+    );
+
+    let program = Interpreter::from_source("TG 300 0 300\nTG 250 50 200\n", Dialect::Rgcf);
+    assert_eq!(
+        kinematics::check_reach(program, &Arm::default(), &RobotProfile::default(), 100),
+        Ok(2)
+    );
+}
+
+#[test]
+fn pose_targets_keep_the_gripper_vertical() {
+    let arm = Arm::default();
+    let limits = kinematics::joint_limits(&RobotProfile::default());
+    let current: Joints = [0.0; 6];
+
+    for (position, orientation) in [
+        ([250.0, 0.0, 100.0], [180.0, 0.0, 0.0]),
+        ([200.0, 100.0, 150.0], [180.0, 0.0, 45.0]),
+        ([300.0, -50.0, 300.0], [150.0, 20.0, -20.0]),
+    ] {
+        let solutions = arm
+            .inverse_pose(position, orientation, &current, &limits)
+            .unwrap();
+        let expected = kinematics::rotation(orientation);
+
+        for joints in &solutions {
+            let pose = arm.forward(joints);
+            assert_near(pose.position, position);
+            for (row, expected) in pose.rotation.iter().zip(expected) {
+                assert_near(*row, expected);
+            }
+        }
+    }
+
+    let down = arm
+        .inverse_pose([250.0, 0.0, 100.0], [180.0, 0.0, 0.0], &current, &limits)
+        .unwrap();
+    assert_near(arm.forward(&down[0]).approach(), [0.0, 0.0, -1.0]);
+
+    assert_eq!(
+        arm.inverse_pose([0.0, 0.0, 900.0], [180.0, 0.0, 0.0], &current, &limits),
+        Err(Unreachable::OutOfReach)
+    );
+    // Every configuration pitches the wrist past its limit
+    assert_eq!(
+        arm.inverse_pose(
+            [300.0, -50.0, 300.0],
+            [90.0, 30.0, -20.0],
+            &current,
+            &limits
+        ),
+        Err(Unreachable::JointLimits)
+    );
+}
+
+#[test]
+fn check_reach_solves_pose_targets() {
+    let source = "TP 250 0 100 180 0 0\nTP 200 100 150 YAW=45\nTP 0 0 900 180 0 0\n";
+    let program = Interpreter::from_source(source, Dialect::Rgcf);
+    let errors = kinematics::check_reach(program, &Arm::default(), &RobotProfile::default(), 100)
+        .unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line, 3);
+    assert_eq!(
+        errors[0].command,
+        Command::TP(0.0, 0.0, 900.0, 180.0, 0.0, 0.0)
+    );
+}
+
+#[test]
+fn reach_follows_commands_one_at_a_time() {
+    let arm = Arm::default();
+    let mut reach = kinematics::Reach::new(&arm, &RobotProfile::default());
+
+    assert_eq!(reach.command(&Command::TG(300.0, 0.0, 300.0)), Ok(()));
+    assert_near(arm.forward(&reach.joints).position, [300.0, 0.0, 300.0]);
+
+    // An unreachable target leaves the joints where they were
+    let joints = reach.joints;
+    assert_eq!(
+        reach.command(&Command::TG(0.0, 0.0, 900.0)),
+        Err(Unreachable::OutOfReach)
+    );
+    assert_eq!(reach.joints, joints);
+
+    assert_eq!(reach.command(&Command::HM(0.0, 0.0, 0.0)), Ok(()));
+    assert_eq!(reach.joints, [0.0; 6]);
+}
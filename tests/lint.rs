@@ -0,0 +1,72 @@
+use roboarm_gcode::interpreter::Interpreter;
+use roboarm_gcode::lint::{self, LintKind, RobotProfile};
+use roboarm_gcode::{Command, Dialect};
+
+fn warnings(source: &str) -> Vec<(usize, LintKind)> {
+    let program = Interpreter::from_source(source, Dialect::Rgcf);
+
+    lint::lint(program, &RobotProfile::default(), 1000)
+        .unwrap()
+        .into_iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.kind))
+        .collect()
+}
+
+#[test]
+fn warns_about_suspicious_programs() {
+    let profile = RobotProfile::default();
+
+    assert_eq!(
+        warnings("TG 1 2 3\nHM 0 0 0\nNO\nTG 1 2 3\nTG 1 2 3\nCL OPEN=90\nMN Y 100"),
+        vec![
+            (1, LintKind::MotionBeforeHoming),
+            (3, LintKind::NoOp),
+            (5, LintKind::DuplicateTarget),
+            (6, LintKind::ClawOutOfRange("OPEN", profile.claw_open)),
+            (7, LintKind::JointLimit("joint Y", profile.y)),
+        ]
+    );
+
+    assert_eq!(
+        warnings("HM 0 0 0\nFS\nTG 1 2 3\nTG 4 5 6\nRS\nTG 1 2 3\nHM 0 0 0\nTG 1 2 3"),
+        vec![
+            (3, LintKind::CommandAfterStop),
+            (5, LintKind::ResetWithoutHoming),
+        ]
+    );
+
+    assert!(warnings("HM 0 0 0\nTG 1 2 3\nCL OPEN=30\nMN B 10 -10").is_empty());
+}
+
+#[test]
+fn both_wrist_moves_share_the_roll_limit() {
+    let profile = RobotProfile::default();
+
+    assert_eq!(
+        warnings("HM 0 0 0\nMN B 10 200\nMN C 200"),
+        vec![
+            (2, LintKind::JointLimit("wrist roll", profile.roll)),
+            (3, LintKind::JointLimit("wrist roll", profile.roll)),
+        ]
+    );
+}
+
+#[test]
+fn diagnostics_print_as_json() {
+    let program = Interpreter::from_source("NO", Dialect::Rgcf);
+    let diagnostics = lint::lint(program, &RobotProfile::default(), 10).unwrap();
+
+    assert_eq!(diagnostics[0].command, Command::NO);
+    assert_eq!(
+        diagnostics[0].to_json(),
+        "{\"severity\":\"warning\",\"file\":null,\"line\":1,\"code\":\"no-op\",\"message\":\"NO does nothing\",\"command\":\"NO\"}"
+    );
+
+    let program = Interpreter::from_source("TG \"1", Dialect::Rgcf);
+    let report = lint::lint(program, &RobotProfile::default(), 10).unwrap_err();
+
+    assert!(
+        lint::error_json(&report.errors[0])
+            .starts_with("{\"severity\":\"error\",\"file\":null,\"line\":1,")
+    );
+}
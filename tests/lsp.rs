@@ -0,0 +1,214 @@
+use roboarm_gcode::json::Json;
+use roboarm_gcode::lsp::{self, Server};
+use std::fs;
+use std::path::PathBuf;
+
+fn workspace(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("roboarm_lsp_{}", name));
+    let _ = fs::remove_dir_all(&directory);
+
+    for (path, contents) in files {
+        let path = directory.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    directory
+}
+
+fn open(server: &mut Server, uri: &str, text: &str) -> Json {
+    let message = Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/didOpen".into()),
+        (
+            "params",
+            Json::object(vec![(
+                "textDocument",
+                Json::object(vec![("uri", uri.into()), ("text", text.into())]),
+            )]),
+        ),
+    ]);
+
+    server.handle(&message).remove(0)
+}
+
+fn request(server: &mut Server, method: &str, uri: &str, line: usize, character: usize) -> Json {
+    let message = Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", 1.into()),
+        ("method", method.into()),
+        (
+            "params",
+            Json::object(vec![
+                ("textDocument", Json::object(vec![("uri", uri.into())])),
+                (
+                    "position",
+                    Json::object(vec![("line", line.into()), ("character", character.into())]),
+                ),
+            ]),
+        ),
+    ]);
+
+    server
+        .handle(&message)
+        .remove(0)
+        .get("result")
+        .unwrap()
+        .clone()
+}
+
+fn labels(result: &Json) -> Vec<String> {
+    result
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item.get("label").unwrap().as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn publishes_errors_and_warnings() {
+    let mut server = Server::default();
+    let published = open(&mut server, "file:///job.rgcf", "HM 0 0 0\nNO\nTG 1 2 Q");
+    let params = published.get("params").unwrap();
+    let diagnostics = params.get("diagnostics").unwrap().as_array().unwrap();
+
+    assert_eq!(
+        published.get("method").unwrap().as_str(),
+        Some("textDocument/publishDiagnostics")
+    );
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "{\"range\":{\"start\":{\"line\":2,\"character\":7},\"end\":{\"line\":2,\"character\":8}},\"severity\":1,\"source\":\"roboarm_gcode\",\"message\":\"'Q' is not a valid number\"}"
+    );
+    assert_eq!(diagnostics[1].get("code").unwrap().as_str(), Some("no-op"));
+}
+
+#[test]
+fn hovers_and_completes() {
+    let mut server = Server::default();
+    let uri = "file:///job.rgcf";
+    open(
+        &mut server,
+        uri,
+        "SUB pick(x)\nEND\nPOSE above 1 2 3\nCL 30\nMN \nCALL p\nTG @a",
+    );
+
+    let hover = request(&mut server, "textDocument/hover", uri, 3, 1);
+    let text = hover.get("contents").unwrap().get("value").unwrap();
+    assert!(text.as_str().unwrap().contains("CL OPEN [FORCE=2]"));
+
+    assert_eq!(
+        labels(&request(&mut server, "textDocument/completion", uri, 4, 3)),
+        vec!["X", "Y", "Z", "A", "B", "C"]
+    );
+    assert_eq!(
+        labels(&request(&mut server, "textDocument/completion", uri, 5, 6)),
+        vec!["pick"]
+    );
+    assert_eq!(
+        labels(&request(&mut server, "textDocument/completion", uri, 6, 5)),
+        vec!["above"]
+    );
+    assert!(
+        labels(&request(&mut server, "textDocument/completion", uri, 3, 5))
+            .contains(&"HOLD=".to_string())
+    );
+}
+
+#[test]
+fn goes_to_definitions_across_includes() {
+    let directory = workspace(
+        "definition",
+        &[
+            (
+                "job.rgcf",
+                "INCLUDE \"lib/moves.rgcf\"\nCALL pick 1\nTG @drop",
+            ),
+            ("lib/moves.rgcf", "SUB pick(x)\n  TG #x 0 0\nEND"),
+            ("poses.rgcf", "; shared\nPOSE drop 1 2 3"),
+        ],
+    );
+    let path = directory.join("job.rgcf");
+    let uri = lsp::path_uri(&path);
+    let mut server = Server::default();
+    open(&mut server, &uri, &fs::read_to_string(&path).unwrap());
+
+    let target = |result: Json| {
+        let uri = result.get("uri").unwrap().as_str().unwrap().to_string();
+        let line = result
+            .get("range")
+            .unwrap()
+            .get("start")
+            .unwrap()
+            .get("line")
+            .unwrap()
+            .as_usize();
+        (lsp::uri_path(&uri).unwrap(), line.unwrap())
+    };
+
+    assert_eq!(
+        target(request(&mut server, "textDocument/definition", &uri, 0, 3)),
+        (directory.join("lib/moves.rgcf"), 0)
+    );
+    assert_eq!(
+        target(request(&mut server, "textDocument/definition", &uri, 1, 7)),
+        (directory.join("lib/moves.rgcf"), 0)
+    );
+    assert_eq!(
+        target(request(&mut server, "textDocument/definition", &uri, 2, 5)),
+        (directory.join("poses.rgcf"), 1)
+    );
+}
+
+#[test]
+fn serves_framed_messages() {
+    let initialize = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\",\"params\":{}}";
+    let exit = "{\"jsonrpc\":\"2.0\",\"method\":\"exit\"}";
+    let input = format!(
+        "Content-Length: {}\r\n\r\n{}Content-Length: {}\r\n\r\n{}",
+        initialize.len(),
+        initialize,
+        exit.len(),
+        exit
+    );
+    let mut output = Vec::new();
+
+    lsp::serve(input.as_bytes(), &mut output).unwrap();
+
+    let mut reader = output.as_slice();
+    let reply = lsp::read_message(&mut reader).unwrap().unwrap();
+    let capabilities = reply.get("result").unwrap().get("capabilities").unwrap();
+
+    assert_eq!(
+        capabilities.get("definitionProvider"),
+        Some(&Json::Bool(true))
+    );
+    assert_eq!(lsp::read_message(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn non_ascii_documents_are_served() {
+    let mut server = Server::default();
+    let uri = "file:///job.rgcf";
+    let published = open(&mut server, uri, "é");
+    let diagnostics = published
+        .get("params")
+        .unwrap()
+        .get("diagnostics")
+        .unwrap()
+        .as_array()
+        .unwrap();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        request(&mut server, "textDocument/hover", uri, 0, 1),
+        Json::Null
+    );
+    assert!(
+        request(&mut server, "textDocument/completion", uri, 0, 1)
+            .as_array()
+            .is_some()
+    );
+}
@@ -0,0 +1,90 @@
+use roboarm_gcode::motion::{ACCELERATION, FEED_RATE};
+use roboarm_gcode::{Axis, Command, Dialect, ParseErrorKind, parse_program, parse_program_as};
+
+#[test]
+fn overrides_wrap_a_single_move() {
+    let program =
+        parse_program("FR 3000\nTG 1 2 3 F=100 ACC=50\nTG 4 5 6\nMN A 90 F=3000").unwrap();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::FR(3000.0),
+            Command::FR(100.0),
+            Command::AC(50.0),
+            Command::TG(1.0, 2.0, 3.0),
+            Command::FR(3000.0),
+            Command::AC(500.0),
+            Command::TG(4.0, 5.0, 6.0),
+            Command::MN(Axis::A(90.0)),
+        ]
+    );
+}
+
+#[test]
+fn rates_follow_the_units() {
+    let program = parse_program("HM 0 0 0\nUN IN\nFR 10\nTR Z=1 F=1\nUN MM\nTR Z=1 F=1").unwrap();
+
+    assert_eq!(program[1], Command::FR(254.0));
+    assert_eq!(program[2], Command::FR(25.4));
+    assert_eq!(program[3], Command::TG(0.0, 0.0, 25.4));
+    assert_eq!(program[4], Command::FR(254.0));
+    assert_eq!(program[5], Command::FR(1.0));
+}
+
+#[test]
+fn joint_move_overrides_follow_the_units() {
+    let program = parse_program("UN IN\nMN X 30 F=2 ACC=1").unwrap();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::FR(50.8),
+            Command::AC(25.4),
+            Command::MN(Axis::X(30.0)),
+            Command::FR(FEED_RATE),
+            Command::AC(ACCELERATION),
+        ]
+    );
+}
+
+#[test]
+fn gcode_feed_is_modal() {
+    let program = parse_program_as(
+        &format!("G1 X0 Y0 Z0 F{}\nG1 X1 F600\nG1 X2 F600", FEED_RATE),
+        Dialect::GCode,
+    )
+    .unwrap();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::TG(0.0, 0.0, 0.0),
+            Command::FR(600.0),
+            Command::TG(1.0, 0.0, 0.0),
+            Command::TG(2.0, 0.0, 0.0),
+        ]
+    );
+}
+
+#[test]
+fn rates_must_be_positive() {
+    let kinds: Vec<ParseErrorKind> =
+        parse_program("FR 0\nAC -1\nTG 1 2 3 F=0\nTG 1 2 3 F=1 F=2\nTR ACC=1 X=1")
+            .unwrap_err()
+            .errors
+            .into_iter()
+            .map(|e| e.kind)
+            .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            ParseErrorKind::NotPositive,
+            ParseErrorKind::NotPositive,
+            ParseErrorKind::NotPositive,
+            ParseErrorKind::DuplicateParameter,
+            ParseErrorKind::UnknownPosition,
+        ]
+    );
+}
@@ -0,0 +1,321 @@
+use roboarm_gcode::parser::{self, parse_line};
+use roboarm_gcode::{Axis, Command, ParseErrorKind};
+
+fn parse(source: &str) -> Command {
+    parse_line(1, source).unwrap().unwrap()
+}
+
+fn arity_error(source: &str) -> (usize, usize) {
+    match parse_line(1, source).unwrap_err().kind {
+        ParseErrorKind::WrongArity { expected, found } => (expected, found),
+        kind => panic!("expected an arity error, got {:?}", kind),
+    }
+}
+
+#[test]
+fn no_op() {
+    assert_eq!(parse("NO"), Command::NO);
+    assert_eq!(arity_error("NO 1"), (0, 1));
+}
+
+#[test]
+fn homing() {
+    assert_eq!(parse("HM 1 2 3"), Command::HM(1.0, 2.0, 3.0));
+    assert_eq!(arity_error("HM 1 2"), (3, 2));
+    assert_eq!(arity_error("HM 1 2 3 4"), (3, 4));
+}
+
+#[test]
+fn target() {
+    assert_eq!(parse("TG 10 20 30"), Command::TG(10.0, 20.0, 30.0));
+    assert_eq!(arity_error("TG 10 20"), (3, 2));
+    assert_eq!(arity_error("TG 10 20 30 40"), (3, 4));
+}
+
+#[test]
+fn pose_target() {
+    assert_eq!(
+        parse("TP 0 0 0"),
+        Command::TP(0.0, 0.0, 0.0, 180.0, 0.0, 0.0)
+    );
+    assert_eq!(
+        parse("TP 10 20 30 90"),
+        Command::TP(10.0, 20.0, 30.0, 90.0, 0.0, 0.0)
+    );
+    assert_eq!(
+        parse("TP 10 20 30 90 5 45"),
+        Command::TP(10.0, 20.0, 30.0, 90.0, 5.0, 45.0)
+    );
+    assert_eq!(arity_error("TP 10 20"), (3, 2));
+    assert_eq!(arity_error("TP 1 2 3 4 5 6 7"), (6, 7));
+}
+
+#[test]
+fn claw() {
+    assert_eq!(parse("CL 1 2 3 4 5"), Command::CL(1.0, 2.0, 3.0, 4.0, 5.0));
+    assert_eq!(arity_error("CL 1 2 3 4"), (5, 4));
+    assert_eq!(arity_error("CL 1 2 3 4 5 6"), (5, 6));
+}
+
+#[test]
+fn manual() {
+    assert_eq!(parse("MN X 1.5"), Command::MN(Axis::X(1.5)));
+    assert_eq!(parse("MN Y -2"), Command::MN(Axis::Y(-2.0)));
+    assert_eq!(parse("MN Z 3"), Command::MN(Axis::Z(3.0)));
+    assert_eq!(parse("MN A 4"), Command::MN(Axis::A(4.0)));
+    assert_eq!(parse("MN B 5 6"), Command::MN(Axis::B(5.0, 6.0)));
+    assert_eq!(parse("MN C 7"), Command::MN(Axis::C(7.0)));
+
+    assert_eq!(arity_error("MN X"), (1, 0));
+    assert_eq!(arity_error("MN X 1 2"), (1, 2));
+    assert_eq!(arity_error("MN B 5"), (2, 1));
+    assert_eq!(arity_error("MN"), (2, 0));
+}
+
+#[test]
+fn return_home() {
+    assert_eq!(parse("RH"), Command::RH);
+    assert_eq!(arity_error("RH 1"), (0, 1));
+}
+
+#[test]
+fn reset() {
+    assert_eq!(parse("RS"), Command::RS);
+    assert_eq!(arity_error("RS 1"), (0, 1));
+}
+
+#[test]
+fn force_stop() {
+    assert_eq!(parse("FS"), Command::FS);
+    assert_eq!(arity_error("FS 1"), (0, 1));
+}
+
+#[test]
+fn last_argument_is_kept() {
+    assert_eq!(parse("TG 1 2 3"), Command::TG(1.0, 2.0, 3.0));
+    assert_eq!(parse("MN B 1 2"), Command::MN(Axis::B(1.0, 2.0)));
+}
+
+#[test]
+fn arity_table_covers_every_mnemonic() {
+    for name in ["NO", "HM", "TG", "CL", "RH", "RS", "FS"] {
+        assert!(parser::arity(name).is_some(), "{} missing", name);
+    }
+    for axis in ["X", "Y", "Z", "A", "B", "C"] {
+        assert!(parser::arity(&format!("MN {}", axis)).is_some());
+    }
+}
+
+#[test]
+fn arity_error_location() {
+    let error = parse_line(4, "  MN B 5").unwrap_err();
+
+    assert_eq!(error.line, 4);
+    assert_eq!(error.columns, 3..9);
+    assert_eq!(error.token, "MN B");
+}
+
+#[test]
+fn reports_every_error() {
+    let report = parser::parse_program("TG 1 2\nXX\nMN Q 1\nTG 1 a 3\n").unwrap_err();
+    let kinds: Vec<ParseErrorKind> = report.errors.into_iter().map(|e| e.kind).collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            ParseErrorKind::WrongArity {
+                expected: 3,
+                found: 2
+            },
+            ParseErrorKind::UnknownMnemonic,
+            ParseErrorKind::UnknownAxis,
+            ParseErrorKind::BadNumber,
+        ]
+    );
+}
+
+#[test]
+fn comments_are_ignored() {
+    assert_eq!(
+        parse("TG 1 2 3 ; above the tray"),
+        Command::TG(1.0, 2.0, 3.0)
+    );
+    assert_eq!(parse("TG 1 (x) 2 (y) 3"), Command::TG(1.0, 2.0, 3.0));
+    assert_eq!(parse("TG 1 2 3;note"), Command::TG(1.0, 2.0, 3.0));
+    assert_eq!(parse_line(1, "; whole line").unwrap(), None);
+    assert_eq!(parse_line(1, "(whole line)").unwrap(), None);
+}
+
+#[test]
+fn blank_lines_and_line_endings() {
+    let expected = vec![Command::HM(0.0, 0.0, 0.0), Command::TG(1.0, 2.0, 3.0)];
+
+    assert_eq!(parse_line(1, "").unwrap(), None);
+    assert_eq!(parse_line(1, "   \t").unwrap(), None);
+    assert_eq!(
+        parser::parse_program("HM 0 0 0\n\n  \nTG 1 2 3").unwrap(),
+        expected
+    );
+    assert_eq!(
+        parser::parse_program("HM 0 0 0\nTG 1 2 3\n").unwrap(),
+        expected
+    );
+    assert_eq!(
+        parser::parse_program("HM 0 0 0\r\nTG 1 2 3\r\n").unwrap(),
+        expected
+    );
+    assert_eq!(parse("TG 1 2 3 ; crlf\r"), Command::TG(1.0, 2.0, 3.0));
+}
+
+#[test]
+fn unterminated_comment() {
+    let error = parse_line(3, "TG 1 2 3 (open").unwrap_err();
+
+    assert_eq!(error.kind, ParseErrorKind::UnterminatedComment);
+    assert_eq!(error.line, 3);
+    assert_eq!(error.columns, 10..15);
+}
+
+#[test]
+fn named_arguments() {
+    assert_eq!(parse("TG Z=80 X=120 Y=40"), Command::TG(120.0, 40.0, 80.0));
+    assert_eq!(parse("TG 120 Z=80 Y=40"), Command::TG(120.0, 40.0, 80.0));
+    assert_eq!(parse("MN B roll=2 pitch=1"), Command::MN(Axis::B(1.0, 2.0)));
+}
+
+#[test]
+fn named_arguments_fall_back_to_defaults() {
+    let Command::CL(open, force, ..) = parse("CL FORCE=3 OPEN=25") else {
+        panic!("expected a claw command");
+    };
+
+    assert_eq!((open, force), (25.0, 3.0));
+}
+
+#[test]
+fn named_argument_errors() {
+    let kind = |source: &str| parse_line(1, source).unwrap_err().kind;
+
+    assert_eq!(
+        kind("TG X=1 Y=2"),
+        ParseErrorKind::MissingParameter("Z".to_string())
+    );
+    assert_eq!(kind("TG X=1 Y=2 W=3"), ParseErrorKind::UnknownParameter);
+    assert_eq!(kind("TG X=1 X=2 Z=3"), ParseErrorKind::DuplicateParameter);
+    assert_eq!(kind("TG X=1 2 3"), ParseErrorKind::PositionalAfterNamed);
+    assert_eq!(kind("TG X=1 Y=a Z=3"), ParseErrorKind::BadNumber);
+}
+
+#[test]
+fn line_numbers_and_checksums() {
+    use roboarm_gcode::executor::frame;
+
+    let line = frame(10, "TG 1 2 3");
+
+    assert_eq!(parse(&line), Command::TG(1.0, 2.0, 3.0));
+    assert_eq!(
+        parse("N20 TG 4 5 6 ; no checksum"),
+        Command::TG(4.0, 5.0, 6.0)
+    );
+    assert_eq!(
+        parse_line(1, "N10 TG 1 2 3*99").unwrap_err().kind,
+        ParseErrorKind::BadChecksum(parser::checksum("N10 TG 1 2 3"))
+    );
+
+    let report = parser::parse_program(&format!("N1 REPEAT 2*0\n{}\nEND", line)).unwrap_err();
+    assert_eq!(report.errors[0].columns, 12..14);
+}
+
+#[test]
+fn line_numbers_must_increase() {
+    // Gaps and unnumbered lines are fine, and a loop body runs again
+    assert_eq!(
+        parser::parse_program("N5 HM 0 0 0\nTG 1 2 3\nN10 REPEAT 2\nN11 TG 4 5 6\nN12 END")
+            .unwrap()
+            .len(),
+        4
+    );
+
+    let report = parser::parse_program("N5 TG 1 2 3\nN3 TG 1 2 3\nN5 TG 1 2 3\nN6 RH").unwrap_err();
+    let errors: Vec<_> = report
+        .errors
+        .iter()
+        .map(|e| (e.line, e.columns.clone(), e.kind.clone()))
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            (2, 1..3, ParseErrorKind::LineNumberOrder(5)),
+            (3, 1..3, ParseErrorKind::LineNumberOrder(5)),
+        ]
+    );
+
+    let report = parser::parse_program("N2 REPEAT 2\nN1 TG 1 2 3\nEND").unwrap_err();
+    assert_eq!(report.errors[0].kind, ParseErrorKind::LineNumberOrder(2));
+}
+
+#[test]
+fn negative_dwells_are_rejected() {
+    assert_eq!(parse("DW 0"), Command::DW(0.0));
+    assert_eq!(
+        parse_line(1, "DW -5").unwrap_err().kind,
+        ParseErrorKind::Negative
+    );
+
+    let report = parser::parse_program("%gcode\nG4 P-100\nG4 S-1\nG4 P0").unwrap_err();
+    let errors: Vec<_> = report
+        .errors
+        .iter()
+        .map(|e| (e.line, e.columns.clone(), e.kind.clone()))
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            (2, 4..9, ParseErrorKind::Negative),
+            (3, 4..7, ParseErrorKind::Negative),
+        ]
+    );
+}
+
+#[test]
+fn non_ascii_lines_are_errors() {
+    for source in ["°", "é 1 2 3", "Ñ10 TG 1 2 3", "TG 1 2 3 ; über"] {
+        let _ = parse_line(1, source);
+    }
+
+    assert_eq!(
+        parse_line(1, "é 1 2 3").unwrap_err().kind,
+        ParseErrorKind::UnknownMnemonic
+    );
+    assert_eq!(
+        parse_line(1, "TG 1 2 3 ; über").unwrap(),
+        Some(Command::TG(1.0, 2.0, 3.0))
+    );
+}
+
+#[test]
+fn non_finite_numbers_are_rejected() {
+    for source in [
+        "DW NaN",
+        "FR inf",
+        "AC infinity",
+        "MN X NaN",
+        "TG 1e39 0 0",
+        "TG [1e39] 0 0",
+        "CL OPEN=-inf",
+    ] {
+        assert_eq!(
+            parse_line(1, source).unwrap_err().kind,
+            ParseErrorKind::BadNumber,
+            "{}",
+            source
+        );
+    }
+
+    // G-code has no exponents, a number too long for f32 overflows all the same
+    let source = format!("%gcode\nG1 X{}", "9".repeat(40));
+    let report = parser::parse_program(&source).unwrap_err();
+    assert_eq!(report.errors[0].kind, ParseErrorKind::BadNumber);
+}
@@ -0,0 +1,70 @@
+use roboarm_gcode::interpreter::Interpreter;
+use roboarm_gcode::poses;
+use roboarm_gcode::{Command, Dialect, ParseErrorKind, parse_program};
+use std::path::Path;
+
+#[test]
+fn targets_named_poses() {
+    let program = parse_program(
+        "POSE pick_above 120 40 150\nUN IN\nPOSE drop 1 2 3\nRP\nTG @Pick_Above F=10\nTG @drop",
+    )
+    .unwrap();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::FR(254.0),
+            Command::TG(120.0, 40.0, 150.0),
+            Command::FR(1000.0),
+            Command::TG(25.4, 50.8, 76.2),
+        ]
+    );
+}
+
+#[test]
+fn poses_load_from_a_pose_file() {
+    let library = poses::load(
+        "; fixture 2\n#z = 150\nPOSE home 0 0 #z\nPOSE home 0 0 [#z + 10]",
+        Path::new("poses.rgcf"),
+    )
+    .unwrap();
+
+    assert_eq!(library.get("home"), Some(&[0.0, 0.0, 160.0]));
+
+    let program: Vec<Command> = Interpreter::from_source("TG @home", Dialect::Rgcf)
+        .with_poses(library)
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(program, vec![Command::TG(0.0, 0.0, 160.0)]);
+}
+
+#[test]
+fn pose_errors() {
+    let report =
+        parse_program("TG @nowhere\nPOSE fixture-2 0 0 0\nPOSE a 1 2\nPOSE b 0 0 0\nTG @b 1")
+            .unwrap_err();
+    let kinds: Vec<ParseErrorKind> = report.errors.into_iter().map(|e| e.kind).collect();
+
+    assert_eq!(kinds[0], ParseErrorKind::UndefinedPose);
+    assert!(matches!(kinds[1], ParseErrorKind::BadExpression(_)));
+    assert_eq!(
+        kinds[2..],
+        [
+            ParseErrorKind::WrongArity {
+                expected: 3,
+                found: 2
+            },
+            ParseErrorKind::WrongArity {
+                expected: 1,
+                found: 2
+            },
+        ]
+    );
+
+    let error = poses::load("POSE a 1 2 3\nTG @b", Path::new("poses.rgcf")).unwrap_err();
+    assert_eq!(
+        error.errors[0].origin.file.as_deref(),
+        Some(Path::new("poses.rgcf"))
+    );
+}
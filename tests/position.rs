@@ -0,0 +1,82 @@
+use roboarm_gcode::{Command, Dialect, ParseErrorKind, parse_program, parse_program_as};
+
+#[test]
+fn relative_targets_resolve_to_absolute_ones() {
+    let program =
+        parse_program("TG 100 50 80\nTR Z=-10\nRP\nTG 5 0 0\nTG 5 5 0\nAP\nTG 1 2 3\nTR 1 1 1")
+            .unwrap();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::TG(100.0, 50.0, 80.0),
+            Command::TG(100.0, 50.0, 70.0),
+            Command::TG(105.0, 50.0, 70.0),
+            Command::TG(110.0, 55.0, 70.0),
+            Command::TG(1.0, 2.0, 3.0),
+            Command::TG(2.0, 3.0, 4.0),
+        ]
+    );
+}
+
+#[test]
+fn pose_targets_take_an_absolute_orientation() {
+    let program =
+        parse_program("TP 100 50 80 180 0 0\nRP\nTP 10 0 -20 ROLL=90\nTP X=0 Y=0 Z=0").unwrap();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::TP(100.0, 50.0, 80.0, 180.0, 0.0, 0.0),
+            Command::TP(110.0, 50.0, 60.0, 90.0, 0.0, 0.0),
+            Command::TP(110.0, 50.0, 60.0, 180.0, 0.0, 0.0),
+        ]
+    );
+}
+
+#[test]
+fn modes_are_shared_between_dialects() {
+    let program = parse_program_as(
+        "HM 0 0 200\nRP\n%gcode\nG1 Z-20\nG90\n%rgcf\nTG 1 1 1",
+        Dialect::Rgcf,
+    )
+    .unwrap();
+
+    assert_eq!(program[1], Command::TG(0.0, 0.0, 180.0));
+    assert_eq!(program[2], Command::TG(1.0, 1.0, 1.0));
+}
+
+#[test]
+fn relative_moves_need_a_known_position() {
+    let kinds: Vec<ParseErrorKind> = parse_program("TR 1 0 0\nTG 0 0 0\nRH\nRP\nTG 1 1 1\nAP 1")
+        .unwrap_err()
+        .errors
+        .into_iter()
+        .map(|e| e.kind)
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            ParseErrorKind::UnknownPosition,
+            ParseErrorKind::UnknownPosition,
+            ParseErrorKind::WrongArity {
+                expected: 0,
+                found: 1
+            },
+        ]
+    );
+}
+
+#[test]
+fn joint_moves_forget_the_position() {
+    let error = parse_program("TG 1 2 3\nMN X 30\nTR 10 0 0").unwrap_err();
+
+    assert_eq!(error.errors.len(), 1);
+    assert_eq!(error.errors[0].line, 3);
+    assert_eq!(error.errors[0].kind, ParseErrorKind::UnknownPosition);
+    assert_eq!(
+        parse_program("TG 1 2 3\nMN X 30\nTG 4 5 6\nTR 10 0 0").unwrap()[3],
+        Command::TG(14.0, 5.0, 6.0)
+    );
+}
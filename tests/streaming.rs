@@ -0,0 +1,84 @@
+use roboarm_gcode::{Command, Dialect, Interpreter, ParseErrorKind};
+use std::io::{self, BufReader, Cursor, Read};
+
+// Endless `TG n 0 0` lines, produced as they are read
+struct Trajectory {
+    next: usize,
+    line: Vec<u8>,
+}
+impl Read for Trajectory {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.line.is_empty() {
+            self.line = format!("TG {} 0 0\r\n", self.next).into_bytes();
+            self.next += 1;
+        }
+
+        let n = buf.len().min(self.line.len());
+        buf[..n].copy_from_slice(&self.line[..n]);
+        self.line.drain(..n);
+
+        Ok(n)
+    }
+}
+
+// Fails after the given text has been read
+struct Failing(Cursor<Vec<u8>>);
+impl Read for Failing {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.read(buf)? {
+            0 => Err(io::Error::new(io::ErrorKind::ConnectionReset, "link lost")),
+            n => Ok(n),
+        }
+    }
+}
+
+#[test]
+fn commands_come_before_the_end_of_input() {
+    let reader = BufReader::new(Trajectory {
+        next: 0,
+        line: Vec::new(),
+    });
+    let program: Vec<Command> = Interpreter::from_reader(reader, Dialect::Rgcf)
+        .skip(100_000)
+        .take(2)
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::TG(100_000.0, 0.0, 0.0),
+            Command::TG(100_001.0, 0.0, 0.0)
+        ]
+    );
+}
+
+#[test]
+fn reads_the_same_as_a_string() {
+    let source = "#n = 0\nWHILE [#n < 3]\n  TG #n 0 0\n  #n = #n + 1\nEND\nTG 9 9 9";
+    let from_reader: Vec<_> =
+        Interpreter::from_reader(Cursor::new(source), Dialect::Rgcf).collect();
+    let from_source: Vec<_> = Interpreter::from_source(source, Dialect::Rgcf).collect();
+
+    assert_eq!(from_reader, from_source);
+}
+
+#[test]
+fn read_errors_end_the_program() {
+    let reader = BufReader::new(Failing(Cursor::new(b"TG 1 2 3\nTG 4 5 6\n".to_vec())));
+    let mut program = Interpreter::from_reader(reader, Dialect::Rgcf);
+
+    assert!(program.next().unwrap().is_ok());
+    assert!(program.next().unwrap().is_ok());
+
+    let error = program.next().unwrap().unwrap_err();
+    assert_eq!(error.line, 3);
+    assert!(matches!(error.kind, ParseErrorKind::ReadFailed(_)));
+    assert!(program.next().is_none());
+
+    let invalid = Cursor::new(b"TG 1 2 3\n\xff\xfe\n".to_vec());
+    let results: Vec<_> = Interpreter::from_reader(invalid, Dialect::Rgcf).collect();
+
+    assert_eq!(results.len(), 2);
+    assert!(matches!(&results[1], Err(e) if e.line == 2));
+}
@@ -0,0 +1,60 @@
+use roboarm_gcode::{Command, ParseErrorKind, parse_program};
+
+const PICK: &str = "#safe_z = 150
+SUB pick(x, y) ; approach, grip, retract
+  TG #x #y #safe_z
+  CL OPEN=0
+  TG #x #y #safe_z
+END
+";
+
+#[test]
+fn calls_expand_into_commands() {
+    let program = parse_program(&format!(
+        "{}CALL pick 100 50\nCALL pick 120 [50 + 10]",
+        PICK
+    ))
+    .unwrap();
+
+    assert_eq!(program.len(), 6);
+    assert_eq!(program[0], Command::TG(100.0, 50.0, 150.0));
+    assert_eq!(program[5], Command::TG(120.0, 60.0, 150.0));
+}
+
+#[test]
+fn call_errors() {
+    let source = format!(
+        "{}CALL place 1 2\nCALL pick 1\nSUB spin\nCALL spin\nEND\nCALL spin\nEND\nSUB open",
+        PICK
+    );
+    let kinds: Vec<ParseErrorKind> = parse_program(&source)
+        .unwrap_err()
+        .errors
+        .into_iter()
+        .map(|e| e.kind)
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            ParseErrorKind::UndefinedSubroutine,
+            ParseErrorKind::WrongArity {
+                expected: 2,
+                found: 1
+            },
+            ParseErrorKind::RecursionLimit,
+            ParseErrorKind::UnexpectedEnd,
+            ParseErrorKind::UnterminatedBlock,
+        ]
+    );
+}
+
+#[test]
+fn duplicate_definitions_point_at_their_keyword() {
+    let source = format!("{}  SUB pick(a, b)\n  END\n", PICK);
+    let error = &parse_program(&source).unwrap_err().errors[0];
+
+    assert_eq!(error.kind, ParseErrorKind::DuplicateDefinition);
+    assert_eq!(error.line, 7);
+    assert_eq!(error.columns, 3..6);
+}
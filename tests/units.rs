@@ -0,0 +1,83 @@
+use roboarm_gcode::units::STEPS_PER_DEGREE_A;
+use roboarm_gcode::{Axis, Command, Dialect, ParseErrorKind, parse_program, parse_program_as};
+
+#[test]
+fn targets_are_lowered_into_millimetres() {
+    let program = parse_program("UN IN\nTG 1 2 0.5\nHM 0 0 10\nUN MM\nTG 1 2 3").unwrap();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::TG(25.4, 50.8, 12.7),
+            Command::HM(0.0, 0.0, 254.0),
+            Command::TG(1.0, 2.0, 3.0),
+        ]
+    );
+}
+
+#[test]
+fn axis_moves_are_lowered_into_degrees() {
+    let program = parse_program(&format!(
+        "UN RAD\nMN B 3.14159265 0\nUN STEP\nMN A {}\nUN DEG\nMN C 45",
+        STEPS_PER_DEGREE_A * 90.0
+    ))
+    .unwrap();
+
+    assert!(matches!(program[0], Command::MN(Axis::B(pitch, 0.0)) if (pitch - 180.0).abs() < 1e-3));
+    assert!(matches!(program[1], Command::MN(Axis::A(angle)) if (angle - 90.0).abs() < 1e-3));
+    assert_eq!(program[2], Command::MN(Axis::C(45.0)));
+}
+
+#[test]
+fn pose_orientations_are_lowered_into_degrees() {
+    let program =
+        parse_program("UN IN\nUN RAD\nTP 1 0 2 3.14159265 0 0\nUN STEP\nTP 1 0 2 0 45 0").unwrap();
+
+    assert!(matches!(program[0], Command::TP(x, 0.0, z, roll, 0.0, 0.0)
+        if x == 25.4 && z == 50.8 && (roll - 180.0).abs() < 1e-3));
+    // Steps belong to joints, an orientation stays in degrees
+    assert_eq!(program[1], Command::TP(25.4, 0.0, 50.8, 0.0, 45.0, 0.0));
+}
+
+#[test]
+fn gcode_inches_carry_over_relative_moves() {
+    let program = parse_program_as(
+        "G21 G1 X10 Y0 Z0\nG20 G91\nG1 X1\nG90\n%rgcf\nTG 1 0 0",
+        Dialect::GCode,
+    )
+    .unwrap();
+
+    assert_eq!(
+        program,
+        vec![
+            Command::TG(10.0, 0.0, 0.0),
+            Command::TG(35.4, 0.0, 0.0),
+            Command::TG(25.4, 0.0, 0.0),
+        ]
+    );
+}
+
+#[test]
+fn unit_errors() {
+    let kinds: Vec<ParseErrorKind> = parse_program("UN FT\nUN\nUN MM IN")
+        .unwrap_err()
+        .errors
+        .into_iter()
+        .map(|e| e.kind)
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            ParseErrorKind::UnknownUnit,
+            ParseErrorKind::WrongArity {
+                expected: 1,
+                found: 0
+            },
+            ParseErrorKind::WrongArity {
+                expected: 1,
+                found: 2
+            },
+        ]
+    );
+}